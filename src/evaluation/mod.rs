@@ -0,0 +1,56 @@
+use crate::game::structs::{board::Board, color::Color, piece::Piece};
+
+use pst::{EG_TABLES, MG_TABLES};
+
+pub mod pst;
+
+/// Middlegame and endgame material values, indexed by `Piece`
+pub const MG_VALUES: [i32; 6] = [0, 1025, 477, 365, 337, 82];
+pub const EG_VALUES: [i32; 6] = [0, 936, 512, 297, 281, 94];
+
+/// Contribution of each piece to the game phase, indexed by `Piece`
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 4, 2, 1, 1, 0];
+/// Game phase with all pieces on the board
+pub const MAX_PHASE: i32 = 24;
+
+/// Returns a static evaluation of a position in centipawns from the side to move perspective.
+/// Material and piece-square scores are blended between middlegame and endgame by the game phase
+pub fn evaluate(game: &Board) -> i32 {
+    let mut mg = 0;
+    let mut eg = 0;
+
+    for color in Color::colors() {
+        let (sign, flip) = match color {
+            Color::White => (1, 56),
+            Color::Black => (-1, 0),
+        };
+        for piece in Piece::pieces() {
+            let p = piece as usize;
+            for square in game.pieces[color][piece] {
+                let index = (square ^ flip) as usize;
+                mg += sign * (MG_VALUES[p] + MG_TABLES[p][index]);
+                eg += sign * (EG_VALUES[p] + EG_TABLES[p][index]);
+            }
+        }
+    }
+
+    let phase = game_phase(game);
+    let score = (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE;
+    match game.turn {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Returns the game phase from `MAX_PHASE` (opening) down to 0 (pawn endgame)
+pub fn game_phase(game: &Board) -> i32 {
+    let phase = Piece::pieces()
+        .iter()
+        .map(|piece| {
+            let count = game.pieces[Color::White][*piece].count() + game.pieces[Color::Black][*piece].count();
+            count as i32 * PHASE_WEIGHTS[*piece as usize]
+        })
+        .sum::<i32>();
+    // Promotions may push the phase above the starting value
+    phase.min(MAX_PHASE)
+}
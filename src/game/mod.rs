@@ -1,2 +1,3 @@
 pub mod moves;
 pub mod structs;
+pub mod notation;
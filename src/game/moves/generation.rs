@@ -1,7 +1,8 @@
 use crate::game::structs::bitboard::Bitboard;
-use crate::game::structs::{board::Board, piece::Piece, color::{Color, Castling}};
+use crate::game::structs::{board::{Board, Undo}, piece::Piece, color::{Color, Castling}, game_state::GameState};
 use crate::shared::errors::ChessError;
-use crate::shared::statics::masks::KING_MASKS;
+use crate::shared::statics::zobrist::{castling_key, en_passant_key, BLACK_MOVE_KEY, PIECE_KEYS};
+use crate::shared::statics::{magics::{bishop_attacks, rook_attacks}, masks::{KING_MASKS, KNIGHT_MASKS, PAWN_CAPTURE_MASKS}, rays::BETWEEN};
 
 use super::move_struct::Flag;
 use super::{individual::*, move_struct::Move};
@@ -11,29 +12,62 @@ use Color::*;
 use Castling::*;
 
 impl Board{
-    /// Generates a list of all pseudolegal moves in a current position
-    fn gen_moves(&self) -> Vec<Move> {
+    /// Generates a list of all legal moves in a current position.
+    /// Checkers and pins are found up front, so no move has to be played to test its legality
+    pub fn gen_legal_moves(&self) -> Vec<Move> {
+        self.gen_moves(true)
+    }
+
+    /// Generates the legal captures, en passant captures and promotions in a current position
+    pub fn gen_legal_captures(&self) -> Vec<Move> {
+        self.gen_moves(false)
+    }
+
+    /// Generates legal moves. Quiet moves, other than promotions, are left out unless `quiet` is set
+    fn gen_moves(&self, quiet: bool) -> Vec<Move> {
         let mut res = vec![];
-        // TODO: there should be only 1 king, so iterating over the bitboard shouldnt be necessary
-        // but right now position isnt validated, so more than 1 king is possible
-        // needs to be implemented later on
-        for from in self.pieces[self.turn][King]{
-            for to in king_moves(from, self, self.turn) {
-                let mv = Move::new(self, from, to, King, self.turn);
-                res.push(mv);
-            }
-            if short_castling(self, self.turn) {
-                let mv = Move::short_castling(from, self.turn);
-                res.push(mv)
-            }
-            if long_castling(self, self.turn) {
-                let mv = Move::long_castling(from, self.turn);
-                res.push(mv)
+        let us = self.turn;
+        let them = !us;
+        let Some(king) = self.pieces[us][King].lsb_index() else {
+            return res
+        };
+
+        let targets = match quiet {
+            true => Bitboard::from(u64::MAX),
+            false => self.enemies(us),
+        };
+
+        // The king is removed from the occupancy, so it can't step back along the line of a slider
+        let without_king = self.all_pieces ^ Bitboard::from(king);
+        for to in king_moves(king, self, us) & targets {
+            if self.attackers(to, them, without_king) == 0 {
+                res.push(Move::new(self, king, to, King, us));
             }
         }
 
-        for piece in Piece::promotable(){
-            for from in self.pieces[self.turn][piece]{
+        let checkers = self.attackers(king, them, self.all_pieces);
+        if checkers.count() > 1 {
+            return res
+        }
+
+        // Squares every other piece is allowed to move to: anywhere when not in check,
+        // otherwise capturing the checker or blocking its line
+        let check_mask = match checkers.lsb_index() {
+            Some(checker) => BETWEEN[king as usize][checker as usize] | checkers,
+            None => {
+                if quiet && castling(self, us, KingSide) {
+                    res.push(Move::short_castling(king, us));
+                }
+                if quiet && castling(self, us, QueenSide) {
+                    res.push(Move::long_castling(king, us));
+                }
+                Bitboard::from(u64::MAX)
+            }
+        };
+        let allowed = self.pin_masks(king, check_mask);
+
+        for piece in Piece::promotable() {
+            for from in self.pieces[us][piece] {
                 let f = match piece {
                     Queen => queen_moves,
                     Rook => rook_moves,
@@ -41,78 +75,113 @@ impl Board{
                     Knight => knight_moves,
                     _ => unreachable!()
                 };
-                for to in f(from, self, self.turn) {
-                    let mv = Move::new(self, from, to, piece, self.turn);
-                    res.push(mv);
+                for to in f(from, self, us) & allowed[from as usize] & targets {
+                    res.push(Move::new(self, from, to, piece, us));
                 }
             }
         }
 
-        for from in self.pieces[self.turn][Pawn]{
-            let end_rank = match self.turn{
-                White => 7,
-                Black => 0,
-            };
-            for to in pawn_moves(from, self, self.turn) {
+        let en_passant = match self.en_passant {
+            Some(square) => Bitboard::from(square),
+            None => Bitboard::empty(),
+        };
+        let end_rank = match us {
+            White => 7,
+            Black => 0,
+        };
+        for from in self.pieces[us][Pawn] {
+            for to in pawn_moves(from, self, us) & allowed[from as usize] & !en_passant {
                 if to / 8 == end_rank {
                     for piece in Piece::promotable() {
-                        let mv = Move::promotion(self, from, to, self.turn, piece);
-                        res.push(mv);
+                        res.push(Move::promotion(self, from, to, us, piece));
                     }
-                } else {
-                    let mv = Move::new(self, from, to, Pawn, self.turn);
-                    res.push(mv);
+                } else if targets.is_set(to) {
+                    res.push(Move::new(self, from, to, Pawn, us));
                 }
             }
+            if pawn_captures(from, self, us) & en_passant != 0 && self.en_passant_is_safe(from, king) {
+                res.push(Move::new(self, from, en_passant.lsb_index().unwrap(), Pawn, us));
+            }
         }
         res
     }
 
-    /// Generates a list of all legal moves in a current position
-    pub fn gen_legal_moves(&self) -> Vec<Move> {
-        self.gen_moves()
-            .into_iter()
-            .filter(|mv| self.is_legal(mv))
-            .collect::<Vec<_>>()
-    }
+    /// Returns the squares each piece of the side to move may go to without exposing its king.
+    /// Pinned pieces are limited to the line between the king and the pinner
+    fn pin_masks(&self, king: u8, check_mask: Bitboard) -> [Bitboard; 64] {
+        let mut res = [check_mask; 64];
+        let them = &self.pieces[!self.turn];
+        let queens = them[Queen];
+        let enemies = self.enemies(self.turn);
 
-    /// Simulates a halfmove and returns whether it is legal or not
-    /// FIXME: 
-    fn is_legal(&self, mv: &Move) -> bool {
-        if mv.piece == King && KING_MASKS[mv.to as usize] & self.pieces[!mv.color][King] != Bitboard::empty() {
-            return false
-        }
-        let mut cloned = self.clone();
-        let res = cloned.make_move(mv);
-        match res {
-            Err(_) => false,
-            Ok(_) => { 
-                // cloned.is_check() != Some(mv.color)
-                !cloned.square_is_attacked(cloned.pieces[mv.color][King].lsb_index().unwrap(), !mv.color)
+        // Sliders that would attack the king if none of our pieces stood in between
+        let snipers = (rook_attacks(king, enemies) & (them[Rook] | queens))
+            | (bishop_attacks(king, enemies) & (them[Bishop] | queens));
+        for sniper in snipers {
+            let line = BETWEEN[king as usize][sniper as usize];
+            let blockers = line & self.all_pieces;
+            if blockers.count() == 1 {
+                let pinned = blockers.lsb_index().unwrap();
+                res[pinned as usize] &= line | Bitboard::from(sniper);
             }
         }
+        res
     }
 
-    /// Makes a move. It is assumed that the passed move is legal
+    /// Returns whether capturing en passant with a pawn leaves the king safe.
+    /// The capture removes two pieces from the board at once, which pin masks don't cover
+    fn en_passant_is_safe(&self, from: u8, king: u8) -> bool {
+        let Some(target) = self.en_passant else {
+            return false
+        };
+        let captured = match self.turn {
+            White => Bitboard::from(target - 8),
+            Black => Bitboard::from(target + 8),
+        };
+        let occupancy = (self.all_pieces ^ Bitboard::from(from) ^ captured) | Bitboard::from(target);
+        self.attackers(king, !self.turn, occupancy) & !captured == 0
+    }
+
+    /// Makes a move. It is assumed that the passed move is legal.
+    /// Returns Err if the game is already finished
     pub fn make_move(&mut self, mv: &Move) -> Result<(), ChessError> {
-        if mv.piece == Pawn || mv.flag.is_castling() {
-            self.repetition_history.clear();
+        if self.state.is_finished() {
+            return Err(ChessError::GameFinished)
         }
+        self.undo_stack.push(Undo {
+            mv: *mv,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+        });
+
+        // a null move only passes the turn, the pieces, castling rights and clocks stay as they are
+        if mv.flag == Flag::Null {
+            self.hash ^= en_passant_key(self.en_passant) ^ *BLACK_MOVE_KEY;
+            self.en_passant = None;
+            self.turn = !self.turn;
+            self.repetition_history.push(self.hash);
+            return Ok(())
+        }
+
         if mv.piece == Pawn || mv.flag.is_capture() {
             self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
         }
+        let old_en_passant = self.en_passant;
         self.en_passant = None;
 
-        let moved_bitboard = &mut self.pieces[mv.color][mv.piece];
         match mv.flag{
-            Flag::Null => (),
+            Flag::Null => unreachable!(),
             Flag::Default => {
-                moved_bitboard.set_0(mv.from);
-                moved_bitboard.set_1(mv.to);
+                self.toggle_piece(mv.color, mv.piece, mv.from);
+                self.toggle_piece(mv.color, mv.piece, mv.to);
             },
             Flag::LongPawnMove => {
-                moved_bitboard.set_0(mv.from);
-                moved_bitboard.set_1(mv.to);
+                self.toggle_piece(mv.color, mv.piece, mv.from);
+                self.toggle_piece(mv.color, mv.piece, mv.to);
                 if check_en_passant(mv.to, self, mv.color) {
                     self.en_passant = match mv.color {
                         White => Some(mv.to - 8),
@@ -121,119 +190,168 @@ impl Board{
                 };
             }
             Flag::Capture(captured) => {
-                moved_bitboard.set_0(mv.from);
-                moved_bitboard.set_1(mv.to);
-                let captured_bitboard = &mut self.pieces[!mv.color][captured];
-                captured_bitboard.set_0(mv.to);
-                self.halfmove_clock = 0;
-                self.repetition_history.clear();
+                self.toggle_piece(mv.color, mv.piece, mv.from);
+                self.toggle_piece(mv.color, mv.piece, mv.to);
+                self.toggle_piece(!mv.color, captured, mv.to);
             }
             Flag::EnPassant => {
-                moved_bitboard.set_0(mv.from);
-                moved_bitboard.set_1(mv.to);
-                let enemy_pawns = &mut self.pieces[!mv.color][Pawn];
+                self.toggle_piece(mv.color, mv.piece, mv.from);
+                self.toggle_piece(mv.color, mv.piece, mv.to);
                 match mv.color {
-                    White => enemy_pawns.set_0(mv.to - 8),
-                    Black => enemy_pawns.set_0(mv.to + 8),
+                    White => self.toggle_piece(Black, Pawn, mv.to - 8),
+                    Black => self.toggle_piece(White, Pawn, mv.to + 8),
                 }
             }
             Flag::Promotion(prom) => {
-                moved_bitboard.set_0(mv.from);
-                let promotion_bitboard = &mut self.pieces[mv.color][prom];
-                promotion_bitboard.set_1(mv.to);
+                self.toggle_piece(mv.color, mv.piece, mv.from);
+                self.toggle_piece(mv.color, prom, mv.to);
             }
             Flag::CapturePromotion(captured, prom) => {
-                moved_bitboard.set_0(mv.from);
-                let captured_bitboard = &mut self.pieces[!mv.color][captured];
-                captured_bitboard.set_0(mv.to);
-                let promotion_bitboard = &mut self.pieces[mv.color][prom];
-                promotion_bitboard.set_1(mv.to);
+                self.toggle_piece(mv.color, mv.piece, mv.from);
+                self.toggle_piece(!mv.color, captured, mv.to);
+                self.toggle_piece(mv.color, prom, mv.to);
             }
-            Flag::ShortCastling => {
-                match mv.color {
-                    White => {
-                        self.pieces[White][Rook].set_0(7);
-                        self.pieces[White][Rook].set_1(5);
-                        self.pieces[White][King].set_0(4);
-                        self.pieces[White][King].set_1(6);
-                    }
-                    Black => {
-                        self.pieces[Black][Rook].set_0(63);
-                        self.pieces[Black][Rook].set_1(61);
-                        self.pieces[Black][King].set_0(60);
-                        self.pieces[Black][King].set_1(62);
-                    }
-                }
-            }
-            Flag::LongCastling => {
-                match mv.color {
-                    White => {
-                        self.pieces[White][Rook].set_0(0);
-                        self.pieces[White][Rook].set_1(3);
-                        self.pieces[White][King].set_0(4);
-                        self.pieces[White][King].set_1(2);
-                    }
-                    Black => {
-                        self.pieces[Black][Rook].set_0(56);
-                        self.pieces[Black][Rook].set_1(59);
-                        self.pieces[Black][King].set_0(60);
-                        self.pieces[Black][King].set_1(58);
-                    }
-                }
+            Flag::ShortCastling | Flag::LongCastling => {
+                let side = if mv.flag == Flag::ShortCastling { KingSide } else { QueenSide };
+                let (_, rook_to) = castling_targets(mv.color, side);
+                // in Chess960 the king or the rook may stay in place or take each other's square,
+                // toggling works in either case since they are on different bitboards
+                self.toggle_piece(mv.color, Rook, self.castling_rooks[mv.color][side]);
+                self.toggle_piece(mv.color, Rook, rook_to);
+                self.toggle_piece(mv.color, King, mv.from);
+                self.toggle_piece(mv.color, King, mv.to);
             }
         }
 
         // deal with castling rights
+        let old_rights = self.castling_rights;
         if mv.piece == King {
-            match mv.color {
-                White => {
-                    self.castling_rights[White][KingSide] = false;
-                    self.castling_rights[White][QueenSide] = false;
-                }
-                Black => {
-                    self.castling_rights[Black][KingSide] = false;
-                    self.castling_rights[Black][QueenSide] = false;
-                }
-            }
+            self.castling_rights[mv.color] = [false; 2];
         }
-        else if mv.piece == Rook {
-            match mv.color {
-                White => {
-                    self.castling_rights[White][QueenSide] = self.castling_rights[White][QueenSide] && mv.from != 0;
-                    self.castling_rights[White][KingSide] = self.castling_rights[White][KingSide] && mv.from != 7;
-                }
-                Black => {
-                    self.castling_rights[Black][QueenSide] = self.castling_rights[Black][QueenSide] && mv.from != 56;
-                    self.castling_rights[Black][KingSide] = self.castling_rights[Black][KingSide] && mv.from != 63;
-
+        // a rook leaving or being captured on its starting square
+        for color in Color::colors() {
+            for side in Castling::sides() {
+                let rook = self.castling_rooks[color][side];
+                if mv.from == rook || mv.to == rook {
+                    self.castling_rights[color][side] = false;
                 }
             }
         }
+        self.hash ^= castling_key(&old_rights, &self.castling_rooks) ^ castling_key(&self.castling_rights, &self.castling_rooks);
 
         self.update_pieces();
+        if self.turn == Black {
+            self.fullmove_number += 1;
+        }
         self.turn = !self.turn;
-        self.repetition_history.push(self.get_hash());
+        self.hash ^= *BLACK_MOVE_KEY;
+
+        // the en passant square is kept only if the capture is legal, so that it is part of
+        // the position for repetition purposes only when it actually changes the available moves
+        if self.en_passant.is_some() && !self.en_passant_is_legal() {
+            self.en_passant = None;
+        }
+        self.hash ^= en_passant_key(old_en_passant) ^ en_passant_key(self.en_passant);
+        self.repetition_history.push(self.hash);
         
         Ok(())
     }
 
-    /// Returns whether the provided square is attacked by the provided side
-    pub fn square_is_attacked(&self, square: u8, color: Color) -> bool {
-        let bitboard = Bitboard::from(square);
-        // let attackers = self.pieces[color];
+    /// Returns whether the side to move can legally capture en passant
+    pub fn en_passant_is_legal(&self) -> bool {
+        let Some(target) = self.en_passant else {
+            return false
+        };
+        let Some(king) = self.pieces[self.turn][King].lsb_index() else {
+            return true
+        };
+        let capturers = PAWN_CAPTURE_MASKS[!self.turn as usize][target as usize] & self.pieces[self.turn][Pawn];
+        capturers.into_iter().any(|from| self.en_passant_is_safe(from, king))
+    }
 
-        if bitboard & all_pawn_captures(self.pieces[color][Pawn], color) != 0 {
-            return true;
+    /// Adds or removes a piece on a square, keeping the hash in sync
+    fn toggle_piece(&mut self, color: Color, piece: Piece, square: u8) {
+        self.pieces[color][piece] ^= Bitboard::from(square);
+        self.hash ^= PIECE_KEYS[square as usize][color as usize][piece as usize];
+    }
+
+    /// Takes back the last move made with `make_move` and returns it.
+    /// Returns `None` if there are no moves to take back
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.undo_stack.pop()?;
+        let mv = undo.mv;
+        // moves can't be made in a finished game, so it was ongoing before this one
+        self.state = GameState::Ongoing;
+        self.repetition_history.pop();
+        self.turn = !self.turn;
+        if self.turn == Black && mv.flag != Flag::Null {
+            self.fullmove_number -= 1;
         }
-        if knight_moves(square, self, !color) & self.pieces[color][Knight] != 0
-            || bishop_moves(square, self, !color) & self.pieces[color][Bishop] != 0 
-            || rook_moves(square, self, !color) & self.pieces[color][Rook] != 0 
-            || queen_moves(square, self, !color) & self.pieces[color][Queen] != 0
-            || king_moves(square, self, !color) & self.pieces[color][King] != 0 {
-            return true
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.hash = undo.hash;
+
+        let moved_bitboard = &mut self.pieces[mv.color][mv.piece];
+        match mv.flag {
+            Flag::Null => (),
+            Flag::Default | Flag::LongPawnMove => {
+                moved_bitboard.set_0(mv.to);
+                moved_bitboard.set_1(mv.from);
+            }
+            Flag::Capture(captured) => {
+                moved_bitboard.set_0(mv.to);
+                moved_bitboard.set_1(mv.from);
+                self.pieces[!mv.color][captured].set_1(mv.to);
+            }
+            Flag::EnPassant => {
+                moved_bitboard.set_0(mv.to);
+                moved_bitboard.set_1(mv.from);
+                let enemy_pawns = &mut self.pieces[!mv.color][Pawn];
+                match mv.color {
+                    White => enemy_pawns.set_1(mv.to - 8),
+                    Black => enemy_pawns.set_1(mv.to + 8),
+                }
+            }
+            Flag::Promotion(prom) => {
+                moved_bitboard.set_1(mv.from);
+                self.pieces[mv.color][prom].set_0(mv.to);
+            }
+            Flag::CapturePromotion(captured, prom) => {
+                moved_bitboard.set_1(mv.from);
+                self.pieces[mv.color][prom].set_0(mv.to);
+                self.pieces[!mv.color][captured].set_1(mv.to);
+            }
+            Flag::ShortCastling | Flag::LongCastling => {
+                let side = if mv.flag == Flag::ShortCastling { KingSide } else { QueenSide };
+                let (_, rook_to) = castling_targets(mv.color, side);
+                self.pieces[mv.color][Rook].set_0(rook_to);
+                self.pieces[mv.color][Rook].set_1(self.castling_rooks[mv.color][side]);
+                self.pieces[mv.color][King].set_0(mv.to);
+                self.pieces[mv.color][King].set_1(mv.from);
+            }
         }
 
-        false
+        self.update_pieces();
+        Some(mv)
+    }
+
+    /// Returns whether the provided square is attacked by the provided side
+    pub fn square_is_attacked(&self, square: u8, color: Color) -> bool {
+        self.attackers(square, color, self.all_pieces) != 0
+    }
+
+    /// Returns the pieces of the provided side attacking a square.
+    /// Sliders are blocked only by the pieces in `occupancy`
+    pub fn attackers(&self, square: u8, color: Color, occupancy: Bitboard) -> Bitboard {
+        let attackers = &self.pieces[color];
+        let queens = attackers[Queen];
+
+        (PAWN_CAPTURE_MASKS[!color as usize][square as usize] & attackers[Pawn])
+            | (KNIGHT_MASKS[square as usize] & attackers[Knight])
+            | (KING_MASKS[square as usize] & attackers[King])
+            | (bishop_attacks(square, occupancy) & (attackers[Bishop] | queens))
+            | (rook_attacks(square, occupancy) & (attackers[Rook] | queens))
     }
 
     /// Returns the color of the checked side in a current position.
@@ -5,10 +5,7 @@ use crate::{
         color::{Castling, Color},
         piece::Piece,
     },
-    shared::{
-        statics::{consts::{FILE_A, FILE_H, RANK_2, RANK_7}, masks::{KING_MASKS, KNIGHT_MASKS, PAWN_CAPTURE_MASKS}, rays::{INCL_RAY, RAY}},
-        structs::DIRECTION,
-    },
+    shared::statics::{consts::{FILE_A, FILE_H, RANK_2, RANK_7}, magics::{bishop_attacks, rook_attacks}, masks::{KING_MASKS, KNIGHT_MASKS, PAWN_CAPTURE_MASKS}, rays::BETWEEN},
 };
 
 use Castling::*;
@@ -40,6 +37,7 @@ pub fn pawn_moves(pawn: u8, game: &Board, color: Color) -> Bitboard {
 }
 
 /// Returns a mask of all capture squares for pawns of a given color
+#[cfg(test)]
 pub fn all_pawn_captures(pawns: Bitboard, color: Color) -> Bitboard {
     match color {
         Color::White => ((pawns << 9) & !*FILE_A) | ((pawns << 7) & !*FILE_H),
@@ -54,35 +52,44 @@ pub fn king_moves(king: u8, game: &Board, color: Color) -> Bitboard {
     mask & !blockers
 }
 
-pub fn short_castling(game: &Board, color: Color) -> bool {
+/// Returns the destination squares of the king and the rook when castling to a side.
+/// They are the same as in standard chess wherever the pieces start
+pub fn castling_targets(color: Color, side: Castling) -> (u8, u8) {
     let index = match color {
         Color::White => 0,
         Color::Black => 56
     };
-
-    game.castling_rights[color][KingSide] 
-    && game.pieces[color][Piece::Rook].is_set(index + 7)
-    && game.empty.is_set(index + 6)
-    && game.empty.is_set(index + 5)
-    && !game.square_is_attacked(index + 4, !color)
-    && !game.square_is_attacked(index + 5, !color)
-    && !game.square_is_attacked(index + 6, !color)
+    match side {
+        KingSide => (index + 6, index + 5),
+        QueenSide => (index + 2, index + 3),
+    }
 }
 
-pub fn long_castling(game: &Board, color: Color) -> bool {
-    let index = match color {
-        Color::White => 0,
-        Color::Black => 56
+/// Returns whether a side can castle, provided that its king isn't in check.
+/// All squares the king and the rook pass must be empty except for the two pieces themselves,
+/// and the king must not pass or land on an attacked square
+pub fn castling(game: &Board, color: Color, side: Castling) -> bool {
+    let rook = game.castling_rooks[color][side];
+    let Some(king) = game.pieces[color][Piece::King].lsb_index() else {
+        return false
     };
+    if !game.castling_rights[color][side] || !game.pieces[color][Piece::Rook].is_set(rook) || king / 8 != rook / 8 {
+        return false
+    }
 
-    game.castling_rights[color][QueenSide] 
-    && game.pieces[color][Piece::Rook].is_set(index)
-    && game.empty.is_set(index + 1)
-    && game.empty.is_set(index + 2)
-    && game.empty.is_set(index + 3)
-    && !game.square_is_attacked(index + 4, !color)
-    && !game.square_is_attacked(index + 3, !color)
-    && !game.square_is_attacked(index + 2, !color)
+    let (king_to, rook_to) = castling_targets(color, side);
+    let movers = Bitboard::from(king) | Bitboard::from(rook);
+    let king_path = BETWEEN[king as usize][king_to as usize] | Bitboard::from(king_to);
+    let rook_path = BETWEEN[rook as usize][rook_to as usize] | Bitboard::from(rook_to);
+    if (king_path | rook_path) & game.all_pieces & !movers != 0 {
+        return false
+    }
+    // The rook is lifted, as in Chess960 it may be shielding the king's path from a slider.
+    // The king's own square is on the path only when the king stays in place
+    let occupancy = game.all_pieces ^ Bitboard::from(rook);
+    king_path
+        .into_iter()
+        .all(|square| game.attackers(square, !color, occupancy) == 0)
 }
 
 /// Returns a bitboard of pseudolegal knight moves of a given color
@@ -94,56 +101,19 @@ pub fn knight_moves(knight: u8, game: &Board, color: Color) -> Bitboard {
 
 /// Returns a bitboard of pseudolegal rook moves of a given color
 pub fn rook_moves(index: u8, game: &Board, color: Color) -> Bitboard {
-    use DIRECTION::*;
-    let e = scan_ray(index, game, color, E);
-    let w = scan_ray(index, game, color, W);
-    let n = scan_ray(index, game, color, N);
-    let s = scan_ray(index, game, color, S);
-
-    e | w | s | n
+    rook_attacks(index, game.all_pieces) & !game.friends(color)
 }
 
+/// Returns a bitboard of pseudolegal bishop moves of a given color
 pub fn bishop_moves(index: u8, game: &Board, color: Color) -> Bitboard {
-    use DIRECTION::*;
-    let ne = scan_ray(index, game, color, NE);
-    let nw = scan_ray(index, game, color, NW);
-    let se = scan_ray(index, game, color, SE);
-    let sw = scan_ray(index, game, color, SW);
-
-    ne | nw | se | sw
+    bishop_attacks(index, game.all_pieces) & !game.friends(color)
 }
 
+/// Returns a bitboard of pseudolegal queen moves of a given color
 pub fn queen_moves(queen: u8, game: &Board, color: Color) -> Bitboard {
     bishop_moves(queen, game, color) | rook_moves(queen, game, color)
 }
 
-fn scan_ray(index: u8, game: &Board, color: Color, direction: DIRECTION) -> Bitboard {
-    let friends = game.friends(color);
-    let enemies = game.enemies(color);
-
-    let blockers = match direction {
-        DIRECTION::E => friends | (enemies << 1),
-        DIRECTION::W => friends | (enemies >> 1),
-        DIRECTION::N => friends | (enemies << 8),
-        DIRECTION::S => friends | (enemies >> 8),
-        DIRECTION::NE => friends | (enemies << 9),
-        DIRECTION::SW => friends | (enemies >> 9),
-        DIRECTION::NW => friends | (enemies << 7),
-        DIRECTION::SE => friends | (enemies >> 7),
-    };
-    let ray = RAY[index as usize][direction as usize];
-    let ray_blocker = match direction {
-        DIRECTION::W | DIRECTION::S | DIRECTION::SE | DIRECTION::SW => (blockers & ray).msb_index(),
-        _ => (blockers & ray).lsb_index(),
-    };
-    match ray_blocker {
-        Some(ray_blocker) => {
-            ray & !Bitboard::from(1u64) & !INCL_RAY[ray_blocker as usize][direction as usize]
-        }
-        None => ray,
-    }
-}
-
 pub fn check_en_passant(pawn: u8, game: &Board, color: Color) -> bool {
     let enemy_pawns = game.pieces[!color][Piece::Pawn];
     let pawn = Bitboard::from(pawn);
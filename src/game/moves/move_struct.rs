@@ -126,20 +126,24 @@ impl Move {
         }
     }
 
+    /// Castling move of a king standing on its back rank.
+    /// The king always lands on the g-file, even in Chess960
     pub fn short_castling(king: u8, color: Color) -> Self {
         Self {
             from: king,
-            to: king + 2,
+            to: king - king % 8 + 6,
             piece: Piece::King,
             color,
             flag: Flag::ShortCastling
         }
     }
 
+    /// Castling move of a king standing on its back rank.
+    /// The king always lands on the c-file, even in Chess960
     pub fn long_castling(king: u8, color: Color) -> Self {
         Self {
             from: king,
-            to: king - 2,
+            to: king - king % 8 + 2,
             piece: Piece::King,
             color,
             flag: Flag::LongCastling
@@ -169,8 +173,8 @@ impl Move {
 }
 
 impl Display for Move {
-    // FIXME: Sometimes when 2 pieces can attack the same square, a starting square letter should
-    // be provided. Right now it is not implemented
+    // NOTE: a move alone doesn't know about the position, so there is no disambiguation
+    // and no check markers. Use `Board::to_san` for proper SAN
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.flag {
             Flag::Null => write!(f, "Null"),
@@ -208,10 +212,10 @@ impl Display for Move {
                 )
             }
             Flag::ShortCastling => {
-                write!(f, "0-0")
+                write!(f, "O-O")
             }
             Flag::LongCastling => {
-                write!(f, "0-0-0")
+                write!(f, "O-O-O")
             }
         }
     }
@@ -0,0 +1,2 @@
+pub mod san;
+pub mod pgn;
@@ -0,0 +1,304 @@
+use crate::{
+    game::{moves::move_struct::Move, structs::{board::Board, color::Color, game_state::GameState}},
+    shared::errors::ChessError,
+};
+
+/// A game read from PGN
+#[derive(Clone)]
+pub struct PgnGame {
+    /// Tag pairs in the order they appear
+    pub tags: Vec<(String, String)>,
+    /// Position the game starts from
+    pub start: Board,
+    /// Position after the last mainline move
+    pub board: Board,
+    /// Mainline moves
+    pub moves: Vec<Move>,
+    /// Game termination marker: `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String,
+}
+
+impl PgnGame {
+    /// Returns the value of a tag pair
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns a record of the game, keeping its tags and result
+    pub fn record(&self) -> GameRecord {
+        let mut record = GameRecord {
+            tags: self.tags.clone(),
+            start_fen: self.start.to_fen(),
+            moves: self.moves.clone(),
+        };
+        record.set_tag("Result", &self.result);
+        record
+    }
+}
+
+/// Maximum length of a movetext line in exported PGN
+const LINE_WIDTH: usize = 80;
+
+/// Tags every PGN game must have, in the order they must appear
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Record of a game: the starting position and the moves played from it
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    /// Tag pairs besides the result and setup tags, which are derived from the game
+    pub tags: Vec<(String, String)>,
+    pub start_fen: String,
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    pub fn new(start: &Board) -> Self {
+        Self {
+            tags: Vec::new(),
+            start_fen: start.to_fen(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Builds a record of all moves made on a board, taking them back to find the starting position
+    pub fn from_board(board: &Board) -> Self {
+        let mut start = board.clone();
+        while start.unmake_move().is_some() {}
+        Self {
+            tags: Vec::new(),
+            start_fen: start.to_fen(),
+            moves: board.undo_stack.iter().map(|undo| undo.mv).collect(),
+        }
+    }
+
+    /// Sets a tag pair, replacing the previous value
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Writes the game as PGN with the Seven Tag Roster and SAN movetext.
+    /// The result is taken from the final position, or from the `Result` tag if the game is ongoing
+    pub fn to_pgn(&self) -> Result<String, ChessError> {
+        let mut board = Board::from_fen(&self.start_fen)?;
+        let mut movetext = Vec::new();
+        for (i, mv) in self.moves.iter().enumerate() {
+            match board.turn {
+                Color::White => movetext.push(format!("{}.", board.fullmove_number)),
+                Color::Black if i == 0 => movetext.push(format!("{}...", board.fullmove_number)),
+                Color::Black => (),
+            }
+            movetext.push(board.to_san(mv));
+            board.make_move(mv)?;
+        }
+
+        board.update_state();
+        let result = match board.state {
+            GameState::Win(Color::White) => "1-0",
+            GameState::Win(Color::Black) => "0-1",
+            GameState::Draw(_) => "1/2-1/2",
+            GameState::Ongoing => self
+                .tags
+                .iter()
+                .find(|(tag, _)| tag == "Result")
+                .map(|(_, value)| value.as_str())
+                .unwrap_or("*"),
+        };
+        movetext.push(result.to_string());
+
+        let mut res = String::new();
+        for name in SEVEN_TAG_ROSTER {
+            let default = match name {
+                "Date" => "????.??.??",
+                "Result" => result,
+                _ => "?",
+            };
+            let value = match name {
+                "Result" => None,
+                _ => self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str()),
+            };
+            res.push_str(&format_tag(name, value.unwrap_or(default)));
+        }
+        if self.start_fen != Board::default().to_fen() {
+            res.push_str(&format_tag("SetUp", "1"));
+            res.push_str(&format_tag("FEN", &self.start_fen));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                res.push_str(&format_tag(name, value));
+            }
+        }
+
+        res.push('\n');
+        let mut line = String::new();
+        for token in movetext {
+            if !line.is_empty() && line.len() + token.len() + 1 > LINE_WIDTH {
+                res.push_str(&line);
+                res.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        res.push_str(&line);
+        res.push('\n');
+        Ok(res)
+    }
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reads the first game of a PGN text and replays its mainline.
+/// Comments, NAGs and variations are skipped
+pub fn read_pgn(pgn: &str) -> Result<PgnGame, ChessError> {
+    let mut tags = Vec::new();
+    let mut movetext = String::new();
+    for line in pgn.lines() {
+        let line = line.trim();
+        // Lines starting with `%` are escaped from parsing
+        if line.starts_with('%') {
+            continue;
+        }
+        if line.starts_with('[') {
+            // Tags after the movetext belong to the next game, which ends this one even without a result
+            if !movetext.trim().is_empty() {
+                break;
+            }
+            tags.push(parse_tag(line)?);
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+
+    let setup = tags.iter().find(|(tag, _)| tag == "SetUp").map(|(_, value)| value.as_str());
+    let fen = tags.iter().find(|(tag, _)| tag == "FEN").map(|(_, value)| value.as_str());
+    let start = match (setup, fen) {
+        (Some("0"), _) | (_, None) => Board::default(),
+        (_, Some(fen)) => Board::from_fen(fen)?,
+    };
+
+    let mut board = start.clone();
+    let mut moves = Vec::new();
+    let mut result = None;
+    let mut depth = 0;
+    for token in tokenize(&movetext) {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" if depth == 0 => return Err(pgn_error("Unmatched closing parenthesis")),
+            ")" => depth -= 1,
+            _ if depth > 0 => continue,
+            "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                result = Some(token);
+                break;
+            }
+            san => {
+                let mv = board.parse_san(san).and_then(|mv| board.make_move(&mv).map(|_| mv));
+                match mv {
+                    Ok(mv) => moves.push(mv),
+                    Err(err) => {
+                        let ply = moves.len() + 1;
+                        let number = match board.turn {
+                            Color::White => format!("{}.", board.fullmove_number),
+                            Color::Black => format!("{}...", board.fullmove_number),
+                        };
+                        return Err(pgn_error(&format!("Ply {} ({}{}): {}", ply, number, san, err)));
+                    }
+                }
+            }
+        }
+    }
+    if depth > 0 {
+        return Err(pgn_error("Unterminated variation"));
+    }
+
+    let result = result
+        .or_else(|| tags.iter().find(|(tag, _)| tag == "Result").map(|(_, value)| value.clone()))
+        .unwrap_or(String::from("*"));
+    Ok(PgnGame { tags, start, board, moves, result })
+}
+
+/// Parses a tag pair like `[Event "Casual game"]`
+fn parse_tag(line: &str) -> Result<(String, String), ChessError> {
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or(pgn_error(&format!("Invalid tag pair: {}", line)))?;
+    let (name, value) = inner
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or(pgn_error(&format!("Invalid tag pair: {}", line)))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or(pgn_error(&format!("Invalid tag value: {}", line)))?;
+    Ok((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Splits movetext into moves, results and parentheses,
+/// dropping comments, NAGs and move numbers
+fn tokenize(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = movetext.chars().peekable();
+
+    let flush = |word: &mut String, tokens: &mut Vec<String>| {
+        // Move numbers may be glued to the move, e.g. `12.Nf3` or `12...Nf3`
+        let token = match word.rfind('.') {
+            Some(i) => &word[i + 1..],
+            None => word.as_str(),
+        };
+        // Annotations like `!?` may also stand apart from the move
+        if !token.is_empty() && !token.chars().all(|c| matches!(c, '!' | '?')) {
+            tokens.push(token.to_string());
+        }
+        word.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                flush(&mut word, &mut tokens);
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                flush(&mut word, &mut tokens);
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '$' => {
+                flush(&mut word, &mut tokens);
+                while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+            }
+            '(' | ')' => {
+                flush(&mut word, &mut tokens);
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => flush(&mut word, &mut tokens),
+            c => word.push(c),
+        }
+    }
+    flush(&mut word, &mut tokens);
+    tokens
+}
+
+fn pgn_error(details: &str) -> ChessError {
+    ChessError::PGNParseError(details.to_string())
+}
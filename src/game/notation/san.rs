@@ -0,0 +1,180 @@
+use std::mem;
+
+use crate::{
+    game::{
+        moves::move_struct::{Flag, Move},
+        structs::{board::Board, game_state::GameState, piece::Piece},
+    },
+    shared::{errors::ChessError, functions::{index_to_square, square_to_index}},
+};
+
+impl Board {
+    /// Returns a legal move in Standard Algebraic Notation, e.g. `Nbd7`, `exd5`, `e8=Q+` or `O-O-O#`.
+    /// The move is made and taken back to find out whether it checks or mates
+    pub fn to_san(&mut self, mv: &Move) -> String {
+        let legal = self.gen_legal_moves();
+        self.to_san_with(mv, &legal)
+    }
+
+    /// Same as `to_san`, with the legal moves of the position already generated,
+    /// so that writing all of them doesn't generate them again for each
+    pub fn to_san_with(&mut self, mv: &Move, legal: &[Move]) -> String {
+        let mut res = match mv.flag {
+            Flag::Null => return String::from("--"),
+            Flag::ShortCastling => String::from("O-O"),
+            Flag::LongCastling => String::from("O-O-O"),
+            _ => {
+                let from = index_to_square(mv.from);
+                let mut res = String::new();
+                match mv.piece {
+                    Piece::Pawn => {
+                        if mv.flag.is_capture() {
+                            res.push_str(&from[..1]);
+                        }
+                    }
+                    piece => {
+                        res.push(piece.char());
+                        res.push_str(&disambiguation(mv, legal));
+                    }
+                }
+                if mv.flag.is_capture() {
+                    res.push('x');
+                }
+                res.push_str(&index_to_square(mv.to));
+                if let Flag::Promotion(prom) | Flag::CapturePromotion(_, prom) = mv.flag {
+                    res.push('=');
+                    res.push(prom.char());
+                }
+                res
+            }
+        };
+
+        // The move is made even if the game was found finished
+        let state = mem::replace(&mut self.state, GameState::Ongoing);
+        if self.make_move(mv).is_ok() {
+            if self.is_check() == Some(self.turn) {
+                match self.gen_legal_moves().is_empty() {
+                    true => res.push('#'),
+                    false => res.push('+'),
+                }
+            }
+            self.unmake_move();
+        }
+        self.state = state;
+        res
+    }
+
+    /// Parses a move in Standard Algebraic Notation and resolves it against the legal moves.
+    /// Common variations are accepted: `0-0` castling, missing `x`, promotions without `=`,
+    /// an explicit `P` for pawns and trailing check markers or annotations like `!?`
+    pub fn parse_san(&self, san: &str) -> Result<Move, ChessError> {
+        let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.gen_legal_moves();
+
+        let castling = match trimmed {
+            "O-O" | "0-0" => Some(Flag::ShortCastling),
+            "O-O-O" | "0-0-0" => Some(Flag::LongCastling),
+            _ => None,
+        };
+        if let Some(flag) = castling {
+            return legal
+                .into_iter()
+                .find(|mv| mv.flag == flag)
+                .ok_or(ChessError::InvalidMove(format!("Illegal move: {}", san)));
+        }
+
+        let invalid = || ChessError::InvalidMove(format!("Couldn't parse move: {}", san));
+        let mut chars = trimmed
+            .chars()
+            .filter(|c| !matches!(c, 'x' | ':' | '-' | '='))
+            .collect::<Vec<char>>();
+
+        let piece = match chars.first() {
+            Some('K') => Some(Piece::King),
+            Some('Q') => Some(Piece::Queen),
+            Some('R') => Some(Piece::Rook),
+            Some('B') => Some(Piece::Bishop),
+            Some('N') => Some(Piece::Knight),
+            Some('P') => Some(Piece::Pawn),
+            _ => None,
+        };
+        if piece.is_some() {
+            chars.remove(0);
+        }
+        let piece = piece.unwrap_or(Piece::Pawn);
+
+        // A letter after the destination rank is a promotion
+        let promotion = match chars.as_slice() {
+            [.., rank, prom] if rank.is_ascii_digit() && prom.is_ascii_alphabetic() => {
+                let prom = match prom.to_ascii_uppercase() {
+                    'Q' => Piece::Queen,
+                    'R' => Piece::Rook,
+                    'B' => Piece::Bishop,
+                    'N' => Piece::Knight,
+                    _ => return Err(invalid()),
+                };
+                chars.pop();
+                Some(prom)
+            }
+            _ => None,
+        };
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(invalid());
+        }
+        let (hint, to) = chars.split_at(chars.len() - 2);
+        let to = square_to_index(&to.iter().collect::<String>()).map_err(|_| invalid())?;
+        let mut file = None;
+        let mut rank = None;
+        for c in hint {
+            match c {
+                'a'..='h' => file = Some(*c as u8 - b'a'),
+                '1'..='8' => rank = Some(*c as u8 - b'1'),
+                _ => return Err(invalid()),
+            }
+        }
+
+        let candidates = legal
+            .into_iter()
+            .filter(|mv| {
+                let mv_promotion = match mv.flag {
+                    Flag::Promotion(prom) | Flag::CapturePromotion(_, prom) => Some(prom),
+                    _ => None,
+                };
+                mv.piece == piece
+                    && mv.to == to
+                    && !mv.flag.is_castling()
+                    && mv_promotion == promotion
+                    && file.is_none_or(|file| mv.from % 8 == file)
+                    && rank.is_none_or(|rank| mv.from / 8 == rank)
+            })
+            .collect::<Vec<_>>();
+
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(ChessError::InvalidMove(format!("Illegal move: {}", san))),
+            _ => Err(ChessError::InvalidMove(format!("Ambiguous move: {}", san))),
+        }
+    }
+}
+
+/// Returns the part of the origin square needed to tell a piece move apart from the moves
+/// of other pieces of the same type to the same square: a file, a rank or the full square
+fn disambiguation(mv: &Move, legal: &[Move]) -> String {
+    let from = index_to_square(mv.from);
+    let others = legal
+        .iter()
+        .filter(|other| other.piece == mv.piece && other.to == mv.to && other.from != mv.from)
+        .map(|other| other.from)
+        .collect::<Vec<_>>();
+
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|other| other % 8 != mv.from % 8) {
+        from[..1].to_string()
+    } else if others.iter().all(|other| other / 8 != mv.from / 8) {
+        from[1..].to_string()
+    } else {
+        from
+    }
+}
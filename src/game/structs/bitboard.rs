@@ -117,6 +117,11 @@ impl Bitboard {
     pub fn is_set(&self, index: u8) -> bool {
         *self & Bitboard::from(index) != 0
     }
+
+    /// Returns the number of 1 bits
+    pub fn count(&self) -> u32 {
+        self.num.count_ones()
+    }
 }
 
 
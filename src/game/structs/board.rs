@@ -1,26 +1,56 @@
-use crate::{game::moves::move_struct::Move, shared::{statics::consts::*, errors::ChessError, functions::{index_to_square, square_to_index}, statics::zobrist::{BLACK_MOVE_KEY, CASTLING_KEYS, PIECE_KEYS}}};
+use crate::{game::moves::move_struct::{Flag, Move}, shared::{statics::consts::*, errors::ChessError, functions::{index_to_square, square_to_index}, statics::zobrist::{castling_key, en_passant_key, BLACK_MOVE_KEY, PIECE_KEYS}}};
 
-use super::{bitboard::Bitboard, color::{Color, Castling}, piece::Piece};
+use super::{bitboard::Bitboard, color::{Color, Castling}, game_state::{DrawReason, GameState}, piece::Piece};
 
 use std::{cell::RefCell, fmt::Display, ops::AddAssign, rc::Rc};
 use Color::*;
 use Piece::*;
 use Castling::*;
 
+/// Placements of the two knights among the five squares left after the bishops and the queen,
+/// in the order of the Chess960 numbering
+const CHESS960_KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// Irreversible state of a board saved before a move, so the move can be taken back.
+/// The captured piece is carried by the move flag
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Undo {
+    pub mv: Move,
+    pub castling_rights: [[bool; 2]; 2],
+    pub en_passant: Option<u8>,
+    pub halfmove_clock: u16,
+    pub hash: u64,
+}
+
 #[derive(Clone)]
 pub struct Board {
-    pub pieces: [[Bitboard; 6]; 2],
-    pub turn: Color,
-    pub castling_rights:[[bool; 2]; 2],
-    pub en_passant: Option<u8>,
+    pub(crate) pieces: [[Bitboard; 6]; 2],
+    pub(crate) turn: Color,
+    pub(crate) castling_rights:[[bool; 2]; 2],
+    /// Starting squares of the rooks the castling rights refer to, indexed by color and side.
+    /// They differ from the corners only in Chess960
+    pub(crate) castling_rooks: [[u8; 2]; 2],
+    /// Whether castling is written in Chess960 style: with rook files in FEN and as
+    /// king-takes-rook in coordinate notation
+    pub(crate) chess960: bool,
+    pub(crate) en_passant: Option<u8>,
     // pub move_history: Vec<Move>,
-    pub halfmove_clock: u8,
-    pub repetition_history: Vec<u64>,
-
-    pub white_pieces: Bitboard,
-    pub black_pieces: Bitboard,
-    pub all_pieces: Bitboard,
-    pub empty: Bitboard,
+    /// Halfmoves since the last capture or pawn move
+    pub(crate) halfmove_clock: u16,
+    /// Number of the current full move, starting at 1 and incremented after Black's move
+    pub(crate) fullmove_number: u16,
+    /// Zobrist hash of the position, updated incrementally by `make_move`
+    pub(crate) hash: u64,
+    /// Hashes of the initial position and of the positions after each move
+    pub(crate) repetition_history: Vec<u64>,
+    pub(crate) undo_stack: Vec<Undo>,
+    /// Result of the game, updated by `update_state`
+    pub(crate) state: GameState,
+
+    pub(crate) white_pieces: Bitboard,
+    pub(crate) black_pieces: Bitboard,
+    pub(crate) all_pieces: Bitboard,
+    pub(crate) empty: Bitboard,
 }
 
 impl Board {
@@ -29,10 +59,16 @@ impl Board {
             pieces: [[Bitboard::empty(); 6]; 2],
             turn: White,
             castling_rights: [[false; 2]; 2],
+            castling_rooks: CASTLING_ROOKS,
+            chess960: false,
             en_passant: None,
             // move_history: Vec::new(),
             halfmove_clock: 0,
-            repetition_history: Vec::new(),
+            fullmove_number: 1,
+            hash: 0,
+            repetition_history: vec![0],
+            undo_stack: Vec::new(),
+            state: GameState::Ongoing,
 
             white_pieces: Bitboard::empty(),
             black_pieces: Bitboard::empty(),
@@ -72,7 +108,7 @@ impl Board {
         Bitboard::from(res)
     }
 
-    pub fn update_pieces(&mut self) {
+    pub(crate) fn update_pieces(&mut self) {
         self.white_pieces = self.white_pieces();
         self.black_pieces = self.black_pieces();
         self.all_pieces = self.white_pieces | self.black_pieces;
@@ -95,13 +131,17 @@ impl Board {
 
     pub fn from_fen(fen: &str) -> Result<Board, ChessError> {
         let mut res = Board::empty();
-        let elements = fen.split(" ").collect::<Vec<&str>>();
+        let elements = fen.split_whitespace().collect::<Vec<&str>>();
         // 1 - board
         // 2 - turn
         // 3 - castling
         // 4 - en passant
         // 5 - halfmove clock
         // 6 - N of full moves
+        // The clocks are often left out (e.g. in EPD), so only the first four fields are required
+        if elements.len() < 4 {
+            return Err(ChessError::FENParseError(fen.to_string(), format!("Expected at least 4 fields, got {}", elements.len())))
+        }
 
         // Parse board
         for (char, i) in elements[0]
@@ -149,16 +189,35 @@ impl Board {
             _ => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid turn character: {}", elements[1])))
         }
 
-        // Parse castling rights
-        for char in elements[2].chars(){
-            match char {
-                'K' => res.castling_rights[White][KingSide] = true,
-                'Q' => res.castling_rights[White][QueenSide] = true,
-                'k' => res.castling_rights[Black][KingSide] = true,
-                'q' => res.castling_rights[Black][QueenSide] = true,
-                '-' => continue,
-            _ => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid castling right character: {char}")))
+        // Parse castling rights. Besides KQkq (the outermost rook on that side of the king, as in X-FEN),
+        // the files of the castling rooks are accepted as in Shredder-FEN
+        for char in elements[2].chars() {
+            if char == '-' {
+                continue
+            }
+            let color = if char.is_ascii_uppercase() { White } else { Black };
+            let back_rank = CASTLING_ROOKS[color][QueenSide];
+            let king = res.pieces[color][King].lsb_index().filter(|king| king / 8 == back_rank / 8);
+            let (side, rook) = match (char.to_ascii_lowercase(), king) {
+                ('k', Some(king)) => (KingSide, res.outermost_rook(color, king, KingSide)),
+                ('q', Some(king)) => (QueenSide, res.outermost_rook(color, king, QueenSide)),
+                ('k', None) => (KingSide, None),
+                ('q', None) => (QueenSide, None),
+                (file @ 'a'..='h', Some(king)) if file as u8 - b'a' != king % 8 => {
+                    let rook = back_rank + file as u8 - b'a';
+                    (if rook > king { KingSide } else { QueenSide }, Some(rook))
+                }
+                _ => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid castling right character: {char}")))
+            };
+            // Castling from anywhere but the standard squares is only possible in Chess960
+            let standard = matches!(char, 'K' | 'Q' | 'k' | 'q')
+                && king == Some(back_rank + 4)
+                && rook == Some(CASTLING_ROOKS[color][side]);
+            if rook.is_some() && !standard {
+                res.chess960 = true;
             }
+            res.castling_rights[color][side] = true;
+            res.castling_rooks[color][side] = rook.unwrap_or(CASTLING_ROOKS[color][side]);
         }
 
         // Parse en passant
@@ -169,30 +228,76 @@ impl Board {
         res.en_passant = en_passant;
 
         // Parse halfmove clock
-        let halfmove_clock = match elements[4] {
-            "" => 0,
-            _ => match elements[4].parse::<u8>() {
+        res.halfmove_clock = match elements.get(4) {
+            None => 0,
+            Some(val) => match val.parse::<u16>() {
                 Ok(val) => val,
-                Err(_) => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid halfmove clock: {}", elements[4])))
+                Err(_) => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid halfmove clock: {}", val)))
         }};
-        res.halfmove_clock = halfmove_clock;
 
         // Parse move count
-        // NOTE: idk what to do with it
-        let _halfmove_clock = match elements[5] {
-            "" => 0,
-            _ => match elements[5].parse::<u8>() {
-                Ok(val) => val,
-                Err(_) => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid halfmove clock: {}", elements[4])))
+        res.fullmove_number = match elements.get(5) {
+            None => 1,
+            Some(val) => match val.parse::<u16>() {
+                Ok(val) => val.max(1),
+                Err(_) => return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid fullmove number: {}", val)))
         }};
 
         res.update_pieces();
+        if let Some(target) = res.en_passant {
+            let (rank, pawn) = match res.turn {
+                White => (5, target.wrapping_sub(8)),
+                Black => (2, target + 8),
+            };
+            if target / 8 != rank || res.find_piece(pawn) != Some((!res.turn, Pawn)) || res.find_piece(target).is_some() {
+                return Err(ChessError::FENParseError(fen.to_string(), format!("Invalid en passant square: {}", elements[3])))
+            }
+            if !res.en_passant_is_legal() {
+                res.en_passant = None;
+            }
+        }
+        res.hash = res.compute_hash();
+        res.repetition_history = vec![res.hash];
         if !res.is_valid() {
             return Err(ChessError::InvalidPosition)
         }
         Ok(res)
     }
 
+    /// Returns the Chess960 start position with a given number from 0 to 959 in the standard numbering,
+    /// in which 518 is the regular start position. Returns `None` for other numbers
+    pub fn chess960(number: u16) -> Option<Board> {
+        if number >= 960 {
+            return None
+        }
+        let mut rank = ['.'; 8];
+        let mut number = number as usize;
+        let free = |rank: &[char; 8]| (0..8).filter(|file| rank[*file] == '.').collect::<Vec<usize>>();
+
+        // One bishop on a light square and one on a dark square
+        rank[number % 4 * 2 + 1] = 'B';
+        number /= 4;
+        rank[number % 4 * 2] = 'B';
+        number /= 4;
+        // The queen and then the knights are put on the free squares counted from the a-file
+        rank[free(&rank)[number % 6]] = 'Q';
+        number /= 6;
+        let (first, second) = CHESS960_KNIGHTS[number];
+        let squares = free(&rank);
+        rank[squares[first]] = 'N';
+        rank[squares[second]] = 'N';
+        // The king always stands between the rooks
+        for (file, piece) in free(&rank).into_iter().zip(['R', 'K', 'R']) {
+            rank[file] = piece;
+        }
+
+        let white = rank.iter().collect::<String>();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", white.to_ascii_lowercase(), white);
+        let mut board = Board::from_fen(&fen).ok()?;
+        board.chess960 = true;
+        Some(board)
+    }
+
     pub fn to_fen(&self) -> String {
         // 1 - board
         // 2 - turn
@@ -234,20 +339,29 @@ impl Board {
             Black => "b".to_string()
         };
 
-        res[2] = match self.castling_rights[White][KingSide]
-            || self.castling_rights[White][QueenSide]
-            || self.castling_rights[Black][KingSide]
-            || self.castling_rights[Black][QueenSide] {
-            false => "-".to_string(),
-            true => {
-                let mut res = String::new();
-                if self.castling_rights[White][KingSide] { res.push('K') };
-                if self.castling_rights[White][QueenSide] { res.push('Q') };
-                if self.castling_rights[Black][KingSide] { res.push('k') };
-                if self.castling_rights[Black][QueenSide] { res.push('q') };
-                res
+        res[2] = String::new();
+        for color in Color::colors() {
+            for side in Castling::sides() {
+                if !self.castling_rights[color][side] {
+                    continue
+                }
+                // Rooks other than the outermost one on their side are written by file
+                let char = match self.castling_rook_char(color, side) {
+                    Some(file) => file,
+                    None => match side {
+                        KingSide => 'K',
+                        QueenSide => 'Q',
+                    },
+                };
+                res[2].push(match color {
+                    White => char,
+                    Black => char.to_ascii_lowercase(),
+                });
             }
-        };
+        }
+        if res[2].is_empty() {
+            res[2].push('-');
+        }
 
         res[3] = match self.en_passant {
             Some(val) => index_to_square(val).to_string(),
@@ -256,12 +370,32 @@ impl Board {
 
         res[4] = self.halfmove_clock.to_string();
 
-        // res[5] = (self.move_history.len() / 2 + 1).to_string();
+        res[5] = self.fullmove_number.to_string();
 
         res.join(" ")
 
     }
     
+    /// Returns the outermost rook of a color on a side of its king, which is what KQkq stand for in X-FEN
+    fn outermost_rook(&self, color: Color, king: u8, side: Castling) -> Option<u8> {
+        let rooks = (self.pieces[color][Rook] & RANKS[king as usize / 8]).into_iter();
+        match side {
+            KingSide => rooks.filter(|rook| *rook > king).max(),
+            QueenSide => rooks.filter(|rook| *rook < king).min(),
+        }
+    }
+
+    /// Returns the file letter a castling right is written with in FEN,
+    /// or `None` if it's written as K or Q
+    fn castling_rook_char(&self, color: Color, side: Castling) -> Option<char> {
+        let rook = self.castling_rooks[color][side];
+        let king = self.pieces[color][King].lsb_index()?;
+        if !self.chess960 || self.outermost_rook(color, king, side) == Some(rook) {
+            return None
+        }
+        Some((b'A' + rook % 8) as char)
+    }
+
     /// Finds a piece at a given index and returns its color and type.
     /// If there is no piece, returns `None`
     pub fn find_piece(&self, index: u8) -> Option<(Color, Piece)>{
@@ -278,16 +412,26 @@ impl Board {
     /// Parses an algebraically notated move into `Move`.
     /// Returns Err if the move string is invalid
     pub fn parse_move(&self, mv: &str) -> Result<Move, ChessError> {
+        if !mv.is_ascii() {
+            return Err(ChessError::InvalidMove(format!("Invalid move: {}", mv)))
+        }
         match mv.len() {
             4 => {
                 if mv == "0000" {
                     return Ok(Move::null())
                 };
                 let (from, to) = mv.split_at(2);
-                let from = square_to_index(from).unwrap();
-                let to = square_to_index(to).unwrap();
+                let from = square_to_index(from)?;
+                let to = square_to_index(to)?;
 
                 if let Some((color, piece)) = self.find_piece(from) {
+                    // Castling may also be written as the king taking its own rook, as it is in Chess960
+                    if piece == King && self.castling_rooks[color].contains(&to) && self.find_piece(to) == Some((color, Rook)) {
+                        return Ok(match self.castling_rooks[color][KingSide] == to {
+                            true => Move::short_castling(from, color),
+                            false => Move::long_castling(from, color),
+                        })
+                    }
                     Ok(Move::new(self, from, to, piece, color))
                 }else{
                     Err(ChessError::InvalidMove(format!("No pieces can make move: {:?}", mv)))
@@ -295,8 +439,8 @@ impl Board {
             }
             5 => {
                 let chars = mv.chars().collect::<Vec<char>>();
-                let from = square_to_index(&chars[0..2].iter().collect::<String>()).unwrap();
-                let to = square_to_index(&chars[2..4].iter().collect::<String>()).unwrap();
+                let from = square_to_index(&chars[0..2].iter().collect::<String>())?;
+                let to = square_to_index(&chars[2..4].iter().collect::<String>())?;
                 let promotion = match chars[4] {
                     'q' => Queen,
                     'r' => Rook,
@@ -318,39 +462,138 @@ impl Board {
         }
     }
 
-    // pub fn update_state(&mut self) {
-    //     let state = if self.halfmove_clock == 100 {
-    //             GameState::Draw
-    //     }else if self.gen_legal_moves().is_empty() {
-    //         if let Some(color) = self.is_check() {
-    //             GameState::Win(!color)
-    //         } else {
-    //             GameState::Draw
-    //         }
-    //     }else{
-    //         let hash = self.get_hash();
-    //         let mut ctr = 0;
-    //         for hash1 in self.repetition_history.iter() {
-    //             if hash == *hash1 {
-    //                 ctr += 1;
-    //             };
-    //         };
-    //         if ctr >= 3 {
-    //             GameState::Draw
-    //         }else{
-    //             GameState::Ongoing
-    //         }
-    //     };
-    //     self.state = state
-    // }
+    /// Returns a move in coordinate notation, like `Move::algebraic`.
+    /// In Chess960 castling is written as the king taking its own rook, since the king may move
+    /// by a single square or not at all
+    pub fn algebraic(&self, mv: &Move) -> String {
+        let side = match mv.flag {
+            Flag::ShortCastling => KingSide,
+            Flag::LongCastling => QueenSide,
+            _ => return mv.algebraic(),
+        };
+        if !self.chess960 {
+            return mv.algebraic()
+        }
+        format!("{}{}", index_to_square(mv.from), index_to_square(self.castling_rooks[mv.color][side]))
+    }
+
+    /// Checks whether the game has ended in the current position and updates its state.
+    /// Once the game is finished, `make_move` refuses to make further moves
+    pub fn update_state(&mut self) {
+        self.state = if self.gen_legal_moves().is_empty() {
+            match self.is_check() {
+                Some(color) => GameState::Win(!color),
+                None => GameState::Draw(DrawReason::Stalemate),
+            }
+        } else if self.is_insufficient_material() {
+            GameState::Draw(DrawReason::InsufficientMaterial)
+        } else if self.halfmove_clock >= 150 {
+            GameState::Draw(DrawReason::SeventyFiveMoves)
+        } else if self.repetitions() >= 4 {
+            GameState::Draw(DrawReason::FivefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            GameState::Draw(DrawReason::FiftyMoves)
+        } else if self.repetitions() >= 2 {
+            GameState::Draw(DrawReason::ThreefoldRepetition)
+        } else {
+            GameState::Ongoing
+        };
+    }
+
+    /// Returns whether neither side can possibly checkmate:
+    /// only kings are left with at most one minor piece, or bishops all on squares of one color
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = self.pieces[White][Queen] | self.pieces[Black][Queen]
+            | self.pieces[White][Rook] | self.pieces[Black][Rook]
+            | self.pieces[White][Pawn] | self.pieces[Black][Pawn];
+        if heavy != 0 {
+            return false
+        }
+        let knights = self.pieces[White][Knight] | self.pieces[Black][Knight];
+        let bishops = self.pieces[White][Bishop] | self.pieces[Black][Bishop];
+        (knights | bishops).count() <= 1
+            || (knights == 0 && (bishops & *DARK_SQUARES == 0 || bishops & !*DARK_SQUARES == 0))
+    }
 
+    /// Returns the Zobrist hash of the position
     pub fn get_hash(&self) -> u64 {
-        let mut hash = 0u64;
+        self.hash
+    }
+
+    /// Returns the side to move
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    /// Returns the squares of the pieces of one type and color
+    pub fn pieces(&self, color: Color, piece: Piece) -> Bitboard {
+        self.pieces[color][piece]
+    }
+
+    /// Returns the occupied squares
+    pub fn occupancy(&self) -> Bitboard {
+        self.all_pieces
+    }
+
+    /// Returns the empty squares
+    pub fn empty_squares(&self) -> Bitboard {
+        self.empty
+    }
+
+    /// Returns whether a side still has the right to castle
+    pub fn castling_right(&self, color: Color, side: Castling) -> bool {
+        self.castling_rights[color][side]
+    }
+
+    /// Returns the square a pawn can be captured on en passant
+    pub fn en_passant(&self) -> Option<u8> {
+        self.en_passant
+    }
+
+    /// Returns the number of halfmoves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Returns the number of the current full move
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    /// Returns whether castling is written in Chess960 style
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Sets whether castling is written in Chess960 style. The position itself is not affected
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    /// Returns the result of the game as of the last `update_state`
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// Returns the hashes of the initial position and of the positions after each move
+    pub fn repetition_history(&self) -> &[u64] {
+        &self.repetition_history
+    }
+
+    /// Returns the moves made on the board since it was set up, in order
+    pub fn history(&self) -> impl Iterator<Item = Move> + '_ {
+        self.undo_stack.iter().map(|undo| undo.mv)
+    }
+
+    /// Returns the starting square of the rook a castling right refers to
+    pub fn castling_rook(&self, color: Color, side: Castling) -> u8 {
+        self.castling_rooks[color][side]
+    }
+
+    /// Computes the Zobrist hash of the position from scratch
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = castling_key(&self.castling_rights, &self.castling_rooks) ^ en_passant_key(self.en_passant);
         if self.turn == Black { hash ^= *BLACK_MOVE_KEY; }
-        if self.castling_rights[White][KingSide] { hash ^= CASTLING_KEYS[0][0]; }
-        if self.castling_rights[White][QueenSide] { hash ^= CASTLING_KEYS[0][1]; }
-        if self.castling_rights[Black][KingSide] { hash ^= CASTLING_KEYS[1][0]; }
-        if self.castling_rights[Black][QueenSide] { hash ^= CASTLING_KEYS[1][1]; }
 
         (0..64).for_each(|square| {
             if let Some((color, piece)) = self.find_piece(square) {
@@ -361,6 +604,23 @@ impl Board {
         hash
     }
 
+    /// Returns how many times the current position occurred before.
+    /// Only positions with the same side to move since the last capture or pawn move can repeat,
+    /// castling rights and en passant captures are covered by the hash.
+    /// A null move doesn't advance the clock, positions before it aren't counted at all
+    pub fn repetitions(&self) -> usize {
+        let clock = self.halfmove_clock as usize;
+        let window = self.undo_stack.iter().rev().take(clock).position(|undo| undo.mv.flag == Flag::Null).unwrap_or(clock);
+        self.repetition_history
+            .iter()
+            .rev()
+            .take(window + 1)
+            .skip(2)
+            .step_by(2)
+            .filter(|hash| **hash == self.hash)
+            .count()
+    }
+
     pub fn is_valid(&self) -> bool {
         // Check if opposite of side isnt in check
         if let Some(color) = self.is_check() {
@@ -403,7 +663,7 @@ impl Default for Board {
                     *BN |
                     *BP;
         let all = black | white;
-        Board {
+        let mut board = Board {
             pieces: [
                 [
                     *WK,
@@ -423,17 +683,26 @@ impl Default for Board {
                 ],
             ],
             castling_rights: [[true; 2]; 2],
+            castling_rooks: CASTLING_ROOKS,
+            chess960: false,
             turn: White,
             en_passant: None,
             // move_history: Vec::new(),
             halfmove_clock: 0,
-            repetition_history: Vec::new(),
+            fullmove_number: 1,
+            hash: 0,
+            repetition_history: vec![0],
+            undo_stack: Vec::new(),
+            state: GameState::Ongoing,
 
             white_pieces: white,
             black_pieces: black,
             all_pieces: all,
             empty: Bitboard::from(u64::MAX - all.num())
-        }
+        };
+        board.hash = board.compute_hash();
+        board.repetition_history = vec![board.hash];
+        board
     }
 }
 
@@ -474,6 +743,9 @@ impl Display for Board {
         if let Some(color) = self.is_check() {
             writeln!(f, "{} checked!", color)?
         }
+        if self.state.is_finished() {
+            writeln!(f, "Game over: {}", self.state)?
+        }
         writeln!(f, "FEN: {}", self.to_fen())?;
         Ok(())
     }
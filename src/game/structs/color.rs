@@ -64,26 +64,33 @@ impl IndexMut<Color> for [[Bitboard; 6]; 2]{
     }
 }
 
-impl Index<Color> for [[bool; 2]; 2]{
-    type Output = [bool; 2];
+impl<T> Index<Color> for [[T; 2]; 2]{
+    type Output = [T; 2];
     fn index(&self, index: Color) -> &Self::Output {
         &self[index as usize]
     }
 }
 
-impl IndexMut<Color> for [[bool; 2]; 2]{
-    fn index_mut(&mut self, index: Color) -> &mut [bool; 2] {
+impl<T> IndexMut<Color> for [[T; 2]; 2]{
+    fn index_mut(&mut self, index: Color) -> &mut [T; 2] {
         &mut self[index as usize]
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Castling {
     KingSide,
     QueenSide
 }
 
-impl Index<Castling> for [bool; 2] {
-    type Output = bool;
+impl Castling {
+    pub fn sides() -> [Castling; 2] {
+        [Castling::KingSide, Castling::QueenSide]
+    }
+}
+
+impl<T> Index<Castling> for [T; 2] {
+    type Output = T;
     fn index(&self, index: Castling) -> &Self::Output {
         match index {
             Castling::KingSide => &self[0],
@@ -92,8 +99,8 @@ impl Index<Castling> for [bool; 2] {
     }
 }
 
-impl IndexMut<Castling> for [bool; 2] {
-    fn index_mut(&mut self, index: Castling) -> &mut bool {
+impl<T> IndexMut<Castling> for [T; 2] {
+    fn index_mut(&mut self, index: Castling) -> &mut T {
         match index {
             Castling::KingSide => &mut self[0],
             Castling::QueenSide => &mut self[1]
@@ -5,12 +5,22 @@ use super::color::Color;
 pub enum GameState{
     Ongoing,
     Win(Color),
-    Draw
+    Draw(DrawReason)
+}
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub enum DrawReason{
+    Stalemate,
+    FiftyMoves,
+    SeventyFiveMoves,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    InsufficientMaterial
 }
 
 impl GameState{
     pub fn is_finished(&self) -> bool {
-        matches!(self, GameState::Win(_) | GameState::Draw)
+        matches!(self, GameState::Win(_) | GameState::Draw(_))
     }
 }
 
@@ -18,8 +28,21 @@ impl Display for GameState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             GameState::Ongoing => write!(f, "Ongoing"),
-            GameState::Draw => write!(f, "Draw"),
+            GameState::Draw(reason) => write!(f, "Draw by {reason}"),
             GameState::Win(color) => write!(f, "{color} won!"),
         }
     }
 }
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "stalemate"),
+            DrawReason::FiftyMoves => write!(f, "fifty-move rule"),
+            DrawReason::SeventyFiveMoves => write!(f, "seventy-five-move rule"),
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+            DrawReason::InsufficientMaterial => write!(f, "insufficient material"),
+        }
+    }
+}
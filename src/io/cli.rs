@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Context};
+use chess_crab::{
+    perft::{default_threads, suite::{parse_suite, run_suite}, PerftTable},
+    Board,
+};
+
+/// Size of the perft hash table when none is provided, in megabytes
+const DEFAULT_PERFT_HASH: usize = 64;
+
+const PERFT_USAGE: &str = "Usage: chess-crab perft <depth> [--threads N] [--hash MB] [fen]";
+const SUITE_USAGE: &str = "Usage: chess-crab perftsuite <file> [--depth N] [--threads N] [--hash MB]";
+
+/// Options shared by the perft subcommands
+struct PerftOptions {
+    depth: Option<u8>,
+    threads: usize,
+    hash: usize,
+    /// Arguments that aren't options
+    rest: Vec<String>,
+}
+
+impl PerftOptions {
+    fn parse(args: &[String], usage: &str) -> anyhow::Result<Self> {
+        let mut options = Self {
+            depth: None,
+            threads: default_threads(),
+            hash: DEFAULT_PERFT_HASH,
+            rest: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(anyhow!(usage.to_string()));
+            match arg.as_str() {
+                "--depth" => options.depth = Some(value()?.parse().context("Invalid depth")?),
+                "--threads" => options.threads = value()?.parse().context("Invalid thread count")?,
+                "--hash" => options.hash = value()?.parse().context("Invalid hash size")?,
+                _ => options.rest.push(arg.clone()),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Runs `chess-crab perft <depth> [--threads N] [--hash MB] [fen]`
+/// and prints the node count and speed
+pub fn perft_cli(args: &[String]) -> anyhow::Result<()> {
+    let options = PerftOptions::parse(args, PERFT_USAGE)?;
+    let (depth, fen) = options.rest.split_first().ok_or(anyhow!(PERFT_USAGE))?;
+    let depth = depth.parse::<u8>().context("Invalid depth")?;
+    let game = match fen.join(" ").trim() {
+        "" | "startpos" => Board::default(),
+        fen => Board::from_fen(fen)?,
+    };
+
+    let result = game.parallel_perft(depth, options.threads, &PerftTable::new(options.hash));
+    println!("Nodes: {}", result.nodes);
+    println!("Time: {} ms", result.time.as_millis());
+    println!("NPS: {}", result.nps());
+    Ok(())
+}
+
+/// Runs `chess-crab perftsuite <file> [--depth N] [--threads N] [--hash MB]`,
+/// checking every position of an EPD perft suite up to a maximum depth
+pub fn perft_suite_cli(args: &[String]) -> anyhow::Result<()> {
+    let options = PerftOptions::parse(args, SUITE_USAGE)?;
+    let path = options.rest.first().ok_or(anyhow!(SUITE_USAGE))?;
+    let epd = std::fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path))?;
+    let entries = parse_suite(&epd)?;
+
+    let table = PerftTable::new(options.hash);
+    let max_depth = options.depth.unwrap_or(u8::MAX);
+    let mismatches = run_suite(&entries, max_depth, options.threads, &table, |entry, mismatches| {
+        if mismatches.is_empty() {
+            println!("ok    {}", entry.fen);
+        }
+        let board = Board::from_fen(&entry.fen).unwrap_or_default();
+        for mismatch in mismatches {
+            println!("FAIL  {} (line {})", entry.fen, entry.line);
+            println!("      depth {}: expected {}, got {}", mismatch.depth, mismatch.expected, mismatch.nodes);
+            for (mv, nodes) in &mismatch.divide {
+                println!("      {}: {}", board.algebraic(mv), nodes);
+            }
+        }
+    })?;
+
+    println!("{} positions, {} failed", entries.len(), mismatches.len());
+    if !mismatches.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
-use crate::shared::functions::square_to_index;
+use chess_crab::square_to_index;
 
-use super::{handlers::{handle_move, handle_moves, handle_position, handle_random, handle_unchecked_move}, state::{Signal, State}};
+use super::{handlers::{handle_divide, handle_go, handle_load, handle_move, handle_moves, handle_perft, handle_position, handle_random, handle_save, handle_tt, handle_unchecked_move}, state::{Signal, State}};
 
 pub fn handle_cmd(state: &mut State, cmd: &Vec<&str>) -> Result<Signal, ()> {
     let mut cmd = cmd.to_vec();
@@ -12,13 +12,16 @@ pub fn handle_cmd(state: &mut State, cmd: &Vec<&str>) -> Result<Signal, ()> {
     match cmd[0] {
         "q" | "quit" | "exit" => Ok(Signal::Exit),
         "moves" =>  {
-            match handle_moves(state.game.borrow(), cmd) {
+            match handle_moves(state.game.borrow_mut(), cmd) {
                 Ok(s) => Ok(s),
                 Err(s) => Ok(s)
             }
         },
         "i" => {
-            Ok(Signal::Message(format!("Index: {}", square_to_index(cmd[1]).unwrap())))
+            match square_to_index(cmd[1]) {
+                Ok(index) => Ok(Signal::Message(format!("Index: {}", index))),
+                Err(err) => Ok(Signal::Message(err.to_string()))
+            }
         }
         "m" => handle_move(state, cmd),
         "position" => {
@@ -28,6 +31,12 @@ pub fn handle_cmd(state: &mut State, cmd: &Vec<&str>) -> Result<Signal, ()> {
             }
         }
         "r" => handle_random(state),
+        "go" => handle_go(state, cmd),
+        "load" => handle_load(state, cmd),
+        "save" => handle_save(state, cmd),
+        "perft" => handle_perft(state, cmd),
+        "divide" => handle_divide(state, cmd),
+        "tt" => handle_tt(state, cmd),
         _ => Ok(Signal::Continue),
     }
 }
@@ -42,13 +51,16 @@ pub fn handle_debug_cmd(state: &mut State, cmd: &Vec<&str>) -> Result<Signal, ()
     match cmd[0] {
         "q" | "quit" | "exit" => Ok(Signal::Exit),
         "moves" =>  {
-            match handle_moves(state.game.borrow(), cmd) {
+            match handle_moves(state.game.borrow_mut(), cmd) {
                 Ok(s) => Ok(s),
                 Err(s) => Ok(s)
             }
         },
         "i" => {
-            Ok(Signal::Message(format!("Index: {}", square_to_index(cmd[1]).unwrap())))
+            match square_to_index(cmd[1]) {
+                Ok(index) => Ok(Signal::Message(format!("Index: {}", index))),
+                Err(err) => Ok(Signal::Message(err.to_string()))
+            }
         }
         "m" => handle_move(state, cmd),
         "um" => handle_unchecked_move(state, cmd),
@@ -59,14 +71,20 @@ pub fn handle_debug_cmd(state: &mut State, cmd: &Vec<&str>) -> Result<Signal, ()
             }
         }
         "r" => handle_random(state),
-        // "state" => {
-        //     Ok(Signal::Message(state.game.borrow().state.to_string()))
-        // }
+        "go" => handle_go(state, cmd),
+        "load" => handle_load(state, cmd),
+        "save" => handle_save(state, cmd),
+        "perft" => handle_perft(state, cmd),
+        "divide" => handle_divide(state, cmd),
+        "tt" => handle_tt(state, cmd),
+        "state" => {
+            Ok(Signal::Message(state.game.borrow().state().to_string()))
+        }
         "hash" => {
             Ok(Signal::Message(state.game.borrow().get_hash().to_string()))
         }
         "hashes" => {
-            Ok(Signal::Message(format!("{:?}", state.game.borrow().repetition_history)))
+            Ok(Signal::Message(format!("{:?}", state.game.borrow().repetition_history())))
         }
         _ => Ok(Signal::Continue),
     }
@@ -1,28 +1,30 @@
-use crate::game::structs::board::Board;
+use chess_crab::{read_pgn, search::SearchLimits, Board, ChessError, GameRecord};
 
-use std::{cell::Ref, ops::Deref} ;
+use std::{cell::RefMut, ops::Deref} ;
 
 use super::state::{Signal, State};
 
+/// Search depth of `go` when none is provided
+const DEFAULT_DEPTH: u8 = 4;
+
 pub fn handle_move(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
-    let game = state.game.borrow();
+    let mut game = state.game.deref().borrow_mut();
+    if game.state().is_finished() {
+        return Ok(Signal::Message(ChessError::GameFinished.to_string()));
+    }
+    // Coordinate notation is tried first, so that e.g. `b1c3` isn't read as a pawn move
     let mv = match game.parse_move(cmd[1]) {
-        Err(_) => {
-            return Ok(Signal::Message(String::from("Incorrect move!")))
-        }
-        Ok(mv) => mv,
+        Ok(mv) if game.gen_legal_moves().contains(&mv) => mv,
+        _ => match game.parse_san(cmd[1]) {
+            Ok(mv) => mv,
+            Err(err) => return Ok(Signal::Message(err.to_string())),
+        },
     };
 
-    if game.gen_legal_moves().contains(&mv) {
-        drop(game);
-        let mut game_mut = state.game.deref().borrow_mut();
-        let move_res = game_mut.make_move(&mv);
-        match move_res {
-            Ok(_) =>  Ok(Signal::Message(String::new())),
-            Err(err) =>  Ok(Signal::Message(err.to_string()))
-        }
-    }else{
-        Ok(Signal::Message("Invalid move!".to_string()))
+    let move_res = game.make_move(&mv);
+    match move_res {
+        Ok(_) =>  Ok(Signal::Message(String::new())),
+        Err(err) =>  Ok(Signal::Message(err.to_string()))
     }
 }
 
@@ -60,17 +62,64 @@ pub fn handle_random(state: &mut State) -> Result<Signal, ()> {
     }
 }
 
-pub fn handle_moves(game: Ref<Board>, cmd: Vec<&str>) -> Result<Signal, Signal> {
+pub fn handle_go(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
+    let depth = match (cmd[1], cmd[2].parse::<u8>()) {
+        ("", _) => DEFAULT_DEPTH,
+        ("depth", Ok(depth)) if depth > 0 => depth,
+        _ => return Ok(Signal::Message("Usage: go depth N".to_string())),
+    };
+
+    let mut game_mut = state.game.deref().borrow_mut();
+    if game_mut.state().is_finished() {
+        return Ok(Signal::Message(ChessError::GameFinished.to_string()));
+    }
+    let result = state.searcher.borrow_mut().search(&mut game_mut, SearchLimits::depth(depth), |_| ());
+    let mv = match result.best_move {
+        Some(mv) => mv,
+        None => return Ok(Signal::Message("No moves available".to_string())),
+    };
+    let san = game_mut.to_san(&mv);
+    if let Err(err) = game_mut.make_move(&mv) {
+        return Ok(Signal::Message(err.to_string()));
+    }
+
+    let score = match result.mate_in() {
+        Some(moves) => format!("#{}", moves),
+        None => format!("{:+.2}", result.score as f32 / 100.0),
+    };
+    Ok(Signal::Message(format!(
+        "Played {} (depth {}, score {}, nodes {})\nPV: {}",
+        san,
+        result.depth,
+        score,
+        result.nodes,
+        result.pv_string(&game_mut)
+    )))
+}
+
+pub fn handle_moves(mut game: RefMut<Board>, cmd: Vec<&str>) -> Result<Signal, Signal> {
     let algebraic = matches!(cmd[1], "a");
-    let msg = game.gen_legal_moves()
+    let legal = game.gen_legal_moves();
+    let msg = legal
         .iter()
         .fold(String::from("Available moves: "), |acc, m| {
-            acc + format!("{} ", if algebraic { m.algebraic() } else { m.to_string() }).as_str()
+            acc + format!("{} ", if algebraic { game.algebraic(m) } else { game.to_san_with(m, &legal) }).as_str()
         });
     Ok(Signal::Message(msg))
 }
 
 pub fn handle_position(cmd: Vec<&str>) -> Result<Signal, Signal> {
+    if cmd[1] == "960" {
+        let number = match cmd[2] {
+            "" => rand::random_range(0..960),
+            number => number.parse::<u16>().unwrap_or(u16::MAX),
+        };
+        return match Board::chess960(number) {
+            Some(pos) => Ok(Signal::Board(Box::from(pos))),
+            None => Err(Signal::Message("Usage: position 960 [0-959]".to_string())),
+        };
+    }
+
     let fen = cmd[1..]
         .iter()
         .map(|s| s.to_string())
@@ -92,3 +141,98 @@ pub fn handle_position(cmd: Vec<&str>) -> Result<Signal, Signal> {
 
     Ok(Signal::Board(Box::from(pos)))
 }
+
+/// Handles `perft N`
+pub fn handle_perft(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
+    let depth = match cmd[1].parse::<u8>() {
+        Ok(depth) => depth,
+        Err(_) => return Ok(Signal::Message("Usage: perft N".to_string())),
+    };
+    let result = state.game.borrow_mut().timed_perft(depth);
+    Ok(Signal::Message(format!(
+        "Nodes: {}\nTime: {} ms\nNPS: {}",
+        result.nodes,
+        result.time.as_millis(),
+        result.nps()
+    )))
+}
+
+/// Handles `divide N`
+pub fn handle_divide(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
+    let depth = match cmd[1].parse::<u8>() {
+        Ok(depth) if depth > 0 => depth,
+        _ => return Ok(Signal::Message("Usage: divide N".to_string())),
+    };
+    let mut game = state.game.borrow_mut();
+    let divide = game.divide(depth);
+    let total = divide.iter().map(|(_, nodes)| nodes).sum::<u64>();
+    let msg = divide
+        .iter()
+        .fold(String::new(), |acc, (mv, nodes)| acc + &format!("{}: {}\n", game.algebraic(mv), nodes));
+    Ok(Signal::Message(format!("{}\nMoves: {}\nNodes: {}", msg, divide.len(), total)))
+}
+
+/// Handles `load pgn <file>`
+pub fn handle_load(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
+    let path = cmd[2..].join(" ").trim().to_string();
+    if cmd[1] != "pgn" || path.is_empty() {
+        return Ok(Signal::Message("Usage: load pgn <file>".to_string()));
+    }
+
+    let pgn = match std::fs::read_to_string(&path) {
+        Ok(pgn) => pgn,
+        Err(err) => return Ok(Signal::Message(format!("Couldn't read {}: {}", path, err))),
+    };
+    let game = match read_pgn(&pgn) {
+        Ok(game) => game,
+        Err(err) => return Ok(Signal::Message(err.to_string())),
+    };
+
+    let msg = format!(
+        "Loaded {} vs {} ({} moves, {})",
+        game.tag("White").unwrap_or("?"),
+        game.tag("Black").unwrap_or("?"),
+        game.moves.len(),
+        game.result
+    );
+    let tags = game.record().tags;
+    state.update_game(Box::new(game.board));
+    state.tags.replace(tags);
+    Ok(Signal::Message(msg))
+}
+
+/// Handles `save pgn <file>`
+pub fn handle_save(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
+    let path = cmd[2..].join(" ").trim().to_string();
+    if cmd[1] != "pgn" || path.is_empty() {
+        return Ok(Signal::Message("Usage: save pgn <file>".to_string()));
+    }
+
+    let mut record = GameRecord::from_board(&state.game.borrow());
+    record.tags = state.tags.borrow().clone();
+    let pgn = match record.to_pgn() {
+        Ok(pgn) => pgn,
+        Err(err) => return Ok(Signal::Message(err.to_string())),
+    };
+    match std::fs::write(&path, pgn) {
+        Ok(_) => Ok(Signal::Message(format!("Saved {} moves to {}", record.moves.len(), path))),
+        Err(err) => Ok(Signal::Message(format!("Couldn't write {}: {}", path, err))),
+    }
+}
+
+/// Handles `tt clear` and `tt resize <MB>`
+pub fn handle_tt(state: &mut State, cmd: Vec<&str>) -> Result<Signal, ()> {
+    let mut searcher = state.searcher.borrow_mut();
+    match (cmd[1], cmd[2].parse::<usize>()) {
+        ("clear", _) => {
+            searcher.clear_hash();
+            Ok(Signal::Message("Transposition table cleared".to_string()))
+        }
+        ("resize", Ok(mb)) if mb > 0 => {
+            searcher.resize_hash(mb);
+            let size = searcher.hash_size() as f64 / (1 << 20) as f64;
+            Ok(Signal::Message(format!("Transposition table size: {:.1} MB", size)))
+        }
+        _ => Ok(Signal::Message("Usage: tt clear | tt resize <MB>".to_string())),
+    }
+}
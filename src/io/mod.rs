@@ -1,3 +1,6 @@
 pub mod state;
+pub mod uci;
+pub mod xboard;
+pub mod cli;
 mod handlers;
 mod cmd;
@@ -3,14 +3,19 @@ use crossterm::{
     execute,
     terminal::{Clear, ClearType},
 };
-use crate::{io::cmd::{handle_cmd, handle_debug_cmd}, game::structs::board::Board};
-use std::
-    borrow::BorrowMut
-;
+use crate::io::cmd::{handle_cmd, handle_debug_cmd};
+use chess_crab::{search::Searcher, Board};
+use std::{
+    borrow::BorrowMut,
+    ops::Deref,
+};
 
 pub struct State {
     pub game: Rc<RefCell<Board>>,
     pub message: Rc<RefCell<String>>,
+    pub searcher: Rc<RefCell<Searcher>>,
+    /// PGN tags of the current game, kept from a loaded PGN
+    pub tags: Rc<RefCell<Vec<(String, String)>>>,
     pub debug: bool
 }
 
@@ -19,6 +24,8 @@ impl State{
         Self{
             game: Rc::new(RefCell::new(game)),
             message: Rc::new(RefCell::new(String::new())),
+            searcher: Rc::new(RefCell::new(Searcher::new())),
+            tags: Rc::new(RefCell::new(Vec::new())),
             debug: std::env::var("DEBUG").is_ok()
         }
     }
@@ -30,6 +37,8 @@ impl State{
     pub fn update_game(&mut self, new_game: Box<Board>){
         let game = self.game.borrow_mut();
         game.replace_with(|_| *new_game);
+        let tags = self.tags.borrow_mut();
+        tags.replace(Vec::new());
     }
 
     // pub fn game_mut_ref(&mut self) -> &mut Rc<RefCell<Board>>{
@@ -56,8 +65,11 @@ pub enum Signal {
     Board(Box<Board>)
 }
 
-pub fn main_loop(state: &mut State) -> anyhow::Result<()> {
+/// Runs the interactive prompt.
+/// `first_cmd` is a line that was already read from stdin and is handled first
+pub fn main_loop(state: &mut State, mut first_cmd: Option<String>) -> anyhow::Result<()> {
     loop {
+        state.game.deref().borrow_mut().update_state();
         execute!(stdout(), Clear(ClearType::All))?;
         let message = state.message.borrow();
         println!("{}", state);
@@ -65,8 +77,16 @@ pub fn main_loop(state: &mut State) -> anyhow::Result<()> {
         stdout().flush()?;
         drop(message);
 
-        let mut buf = String::new();
-        std::io::stdin().read_line(&mut buf).unwrap();
+        let buf = match first_cmd.take() {
+            Some(buf) => buf,
+            None => {
+                let mut buf = String::new();
+                if std::io::stdin().read_line(&mut buf)? == 0 {
+                    break;
+                }
+                buf
+            }
+        };
         let cmd = buf.trim().split(" ").map(|s| s.trim()).collect::<Vec<_>>();
 
         let res = match state.debug {
@@ -0,0 +1,253 @@
+use std::{
+    io::{stdin, stdout, BufRead, Write},
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use chess_crab::{
+    search::{DEFAULT_TT_SIZE, SearchLimits, SearchResult, Searcher},
+    Board, ChessError, Color,
+};
+
+pub const ENGINE_NAME: &str = "ChessCrab";
+pub const ENGINE_AUTHOR: &str = "madeinheaven91";
+
+/// Largest transposition table the `Hash` option accepts, in megabytes
+const MAX_TT_SIZE: usize = 65536;
+
+/// State of a UCI session
+pub struct Uci {
+    pub game: Board,
+    /// Idle searcher. It is moved to the search thread while a search is running
+    searcher: Option<Searcher>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    stop: Arc<AtomicBool>,
+    /// Value of the `UCI_Chess960` option
+    chess960: bool,
+}
+
+impl Uci {
+    pub fn new() -> Self {
+        let searcher = Searcher::new();
+        Self {
+            game: Board::default(),
+            stop: searcher.stop.clone(),
+            searcher: Some(searcher),
+            search_thread: None,
+            chess960: false,
+        }
+    }
+
+    /// Handles a single line of UCI input.
+    /// Returns `false` when the session should end
+    pub fn handle(&mut self, cmd: &[&str]) -> bool {
+        match cmd[0] {
+            "uci" => {
+                println!("id name {} {}", ENGINE_NAME, env!("CARGO_PKG_VERSION"));
+                println!("id author {}", ENGINE_AUTHOR);
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_TT_SIZE, MAX_TT_SIZE);
+                println!("option name Clear Hash type button");
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop();
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear_hash();
+                }
+                self.game = Board::default();
+                self.game.set_chess960(self.chess960);
+            }
+            "position" => {
+                self.stop();
+                if let Err(err) = self.handle_position(&cmd[1..]) {
+                    println!("info string {}", err.to_string().replace('\n', " "));
+                }
+            }
+            "go" => {
+                self.stop();
+                self.handle_go(&cmd[1..]);
+            }
+            "stop" => self.stop(),
+            "setoption" => {
+                self.stop();
+                self.handle_setoption(&cmd[1..]);
+            }
+            "quit" => {
+                self.stop();
+                return false;
+            }
+            _ => println!("info string Unknown command: {}", cmd.join(" ")),
+        }
+        true
+    }
+
+    /// Handles `position [startpos | fen <fen>] [moves <move>...]`
+    fn handle_position(&mut self, args: &[&str]) -> Result<(), ChessError> {
+        let moves_at = args.iter().position(|s| *s == "moves").unwrap_or(args.len());
+        let (pos, moves) = args.split_at(moves_at);
+
+        let mut game = match pos.first() {
+            Some(&"startpos") => Board::default(),
+            Some(&"fen") => Board::from_fen(&pos[1..].join(" "))?,
+            _ => return Err(ChessError::InvalidMove(format!("Invalid position command: {}", args.join(" ")))),
+        };
+        // Chess960 is also recognised from the castling rights, e.g. a Shredder-FEN
+        if self.chess960 {
+            game.set_chess960(true);
+        }
+
+        for mv in moves.iter().skip(1) {
+            let parsed = game.parse_move(mv)?;
+            if !game.gen_legal_moves().contains(&parsed) {
+                return Err(ChessError::InvalidMove(format!("Illegal move: {}", mv)));
+            }
+            game.make_move(&parsed)?;
+        }
+
+        self.game = game;
+        Ok(())
+    }
+
+    /// Handles `go` by starting a search in a separate thread
+    fn handle_go(&mut self, args: &[&str]) {
+        let limits = self.parse_limits(args);
+        let mut game = self.game.clone();
+        let root = self.game.clone();
+        let mut searcher = self.searcher.take().unwrap_or_default();
+        self.stop = searcher.stop.clone();
+        self.stop.store(false, Ordering::Relaxed);
+
+        self.search_thread = Some(std::thread::spawn(move || {
+            let result = searcher.search(&mut game, limits, |result| print_info(result, &root));
+            match result.best_move {
+                Some(mv) => println!("bestmove {}", root.algebraic(&mv)),
+                None => println!("bestmove 0000"),
+            }
+            let _ = stdout().flush();
+            searcher
+        }));
+    }
+
+    /// Parses the arguments of `go` into search limits
+    fn parse_limits(&self, args: &[&str]) -> SearchLimits {
+        let mut limits = SearchLimits::default();
+        let mut time = [None, None];
+        let mut inc = [Duration::ZERO, Duration::ZERO];
+        let mut movestogo = None;
+
+        let value = |i: usize| args.get(i + 1).and_then(|s| s.parse::<u64>().ok());
+        for (i, arg) in args.iter().enumerate() {
+            match *arg {
+                "depth" => limits.depth = value(i).map(|d| d.min(u8::MAX as u64) as u8),
+                "nodes" => limits.nodes = value(i),
+                "movetime" => limits.movetime = value(i).map(Duration::from_millis),
+                "infinite" => limits.infinite = true,
+                "wtime" => time[0] = value(i).map(Duration::from_millis),
+                "btime" => time[1] = value(i).map(Duration::from_millis),
+                "winc" => inc[0] = value(i).map(Duration::from_millis).unwrap_or_default(),
+                "binc" => inc[1] = value(i).map(Duration::from_millis).unwrap_or_default(),
+                "movestogo" => movestogo = value(i).map(|m| m as u32),
+                _ => (),
+            }
+        }
+
+        let side = match self.game.turn() {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        if let (Some(time), None) = (time[side], limits.movetime) {
+            limits.movetime = SearchLimits::from_clock(time, inc[side], movestogo).movetime;
+        }
+        limits
+    }
+
+    /// Stops a running search and waits for it to report its best move
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    /// Waits for a running search to finish
+    fn wait(&mut self) {
+        if let Some(thread) = self.search_thread.take() {
+            self.searcher = Some(thread.join().unwrap_or_default());
+        }
+    }
+
+    /// Handles `setoption name <id> [value <x>]`
+    fn handle_setoption(&mut self, args: &[&str]) {
+        let value_at = args.iter().position(|s| *s == "value").unwrap_or(args.len());
+        let name = args[..value_at].iter().skip(1).copied().collect::<Vec<_>>().join(" ");
+        let value = args.get(value_at + 1).copied().unwrap_or_default();
+        match name.as_str() {
+            "Hash" => match (value.parse::<usize>(), &mut self.searcher) {
+                (Ok(mb), Some(searcher)) => searcher.resize_hash(mb.clamp(1, MAX_TT_SIZE)),
+                _ => println!("info string Invalid hash size: {}", value),
+            },
+            "Clear Hash" => {
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear_hash();
+                }
+            }
+            "UCI_Chess960" => {
+                self.chess960 = value == "true";
+                self.game.set_chess960(self.chess960);
+            }
+            _ => println!("info string Unknown option: {}", name),
+        }
+    }
+}
+
+/// Prints a UCI `info` line for a finished iteration
+fn print_info(result: &SearchResult, game: &Board) {
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let millis = result.time.as_millis().max(1);
+    println!(
+        "info depth {} seldepth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        result.depth,
+        result.seldepth,
+        score,
+        result.nodes,
+        result.nodes as u128 * 1000 / millis,
+        result.hashfull,
+        result.time.as_millis(),
+        result.pv_string(game)
+    );
+    let _ = stdout().flush();
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs a UCI session over stdin/stdout.
+/// `first` is a line that was already read from stdin before the session started
+pub fn uci_loop(first: Option<String>) -> anyhow::Result<()> {
+    let mut uci = Uci::new();
+    let lines = first
+        .into_iter()
+        .chain(stdin().lock().lines().map_while(Result::ok));
+
+    for line in lines {
+        let cmd = line.split_whitespace().collect::<Vec<_>>();
+        if cmd.is_empty() {
+            continue;
+        }
+        let running = uci.handle(&cmd);
+        stdout().flush()?;
+        if !running {
+            return Ok(());
+        }
+    }
+    // Input was closed without `quit`
+    uci.handle(&["quit"]);
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use std::{
+    io::{stdin, stdout, BufRead, Write},
+    time::Duration,
+};
+
+use chess_crab::{
+    search::{SearchLimits, SearchResult, Searcher},
+    square_to_index, Board, ChessError, Color, DrawReason, GameState, Move,
+};
+
+use crate::io::uci::ENGINE_NAME;
+
+/// State of a CECP (xboard/winboard) session
+pub struct XBoard {
+    pub game: Board,
+    /// Side played by the engine
+    pub engine: Color,
+    /// In force mode the engine only checks moves and never plays on its own
+    pub force: bool,
+    /// Print thinking output
+    pub post: bool,
+    /// Depth limit set by `sd`
+    pub depth: Option<u8>,
+    /// Fixed time per move set by `st`
+    pub movetime: Option<Duration>,
+    /// Engine's remaining clock time set by `time`
+    pub clock: Option<Duration>,
+    /// Increment set by `level`
+    pub inc: Duration,
+    searcher: Searcher,
+}
+
+impl XBoard {
+    pub fn new() -> Self {
+        Self {
+            game: Board::default(),
+            engine: Color::Black,
+            force: false,
+            post: false,
+            depth: None,
+            movetime: None,
+            clock: None,
+            inc: Duration::ZERO,
+            searcher: Searcher::new(),
+        }
+    }
+
+    /// Handles a single line of CECP input.
+    /// Returns `false` when the session should end
+    pub fn handle(&mut self, cmd: &[&str]) -> bool {
+        match cmd[0] {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name"
+            | "rating" | "ics" | "otim" | "draw" => (),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "sd" => self.depth = cmd.get(1).and_then(|d| d.parse::<u8>().ok()),
+            "st" => self.movetime = cmd.get(1).and_then(|t| t.parse::<u64>().ok()).map(Duration::from_secs),
+            "time" => self.clock = cmd.get(1).and_then(|t| t.parse::<u64>().ok()).map(|cs| Duration::from_millis(cs * 10)),
+            "level" => self.inc = cmd.get(3).and_then(|t| t.parse::<f64>().ok()).map(Duration::from_secs_f64).unwrap_or_default(),
+            "protover" => {
+                println!(
+                    "feature myname=\"{} {}\" usermove=1 setboard=1 ping=1 colors=0 sigint=0 sigterm=0 reuse=1 analyze=0 memory=1 done=1",
+                    ENGINE_NAME,
+                    env!("CARGO_PKG_VERSION")
+                );
+            }
+            "memory" => {
+                if let Some(mb) = cmd.get(1).and_then(|mb| mb.parse::<usize>().ok()) {
+                    self.searcher.resize_hash(mb);
+                }
+            }
+            "new" => {
+                self.searcher.clear_hash();
+                self.game = Board::default();
+                self.engine = Color::Black;
+                self.force = false;
+                // A new game also removes the depth and time limits
+                self.depth = None;
+                self.movetime = None;
+                self.clock = None;
+                self.inc = Duration::ZERO;
+            }
+            "force" | "result" => self.force = true,
+            "go" => {
+                self.engine = self.game.turn();
+                self.force = false;
+                self.engine_move();
+            }
+            "?" => {
+                if !self.force && self.engine == self.game.turn() {
+                    self.engine_move();
+                }
+            }
+            "playother" => {
+                self.engine = !self.game.turn();
+                self.force = false;
+            }
+            "usermove" => self.user_move(cmd.get(1).copied().unwrap_or("")),
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "setboard" => match Board::from_fen(&cmd[1..].join(" ")) {
+                Ok(game) => self.game = game,
+                Err(err) => println!("tellusererror Illegal position: {}", err.to_string().replace('\n', " ")),
+            },
+            "ping" => println!("pong {}", cmd.get(1).copied().unwrap_or("")),
+            "quit" => return false,
+            // Moves may come without the `usermove` prefix if the interface ignored the feature
+            mv if is_coordinate_move(mv) => self.user_move(mv),
+            _ => println!("Error (unknown command): {}", cmd[0]),
+        }
+        true
+    }
+
+    fn user_move(&mut self, mv: &str) {
+        let parsed = match self.legal_move(mv) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                println!("Illegal move: {}", mv);
+                return;
+            }
+        };
+        self.play(&parsed);
+        if !self.force && self.engine == self.game.turn() {
+            self.engine_move();
+        }
+    }
+
+    fn legal_move(&mut self, mv: &str) -> Result<Move, ChessError> {
+        if self.game.state().is_finished() {
+            return Err(ChessError::GameFinished);
+        }
+        if !is_coordinate_move(mv) {
+            return Err(ChessError::InvalidMove(format!("Invalid move: {}", mv)));
+        }
+        let parsed = self.game.parse_move(mv)?;
+        if self.game.gen_legal_moves().contains(&parsed) {
+            Ok(parsed)
+        } else {
+            Err(ChessError::InvalidMove(format!("Illegal move: {}", mv)))
+        }
+    }
+
+    /// Searches and plays a move for the engine
+    fn engine_move(&mut self) {
+        let post = self.post;
+        let limits = self.limits();
+        let root = self.game.clone();
+        let result = self.searcher.search(&mut self.game, limits, |result| {
+            if post {
+                print_thinking(result, &root)
+            }
+        });
+        match result.best_move {
+            Some(mv) => {
+                println!("move {}", self.game.algebraic(&mv));
+                self.play(&mv);
+            }
+            None => self.report_result(),
+        }
+    }
+
+    /// Returns search limits from the time control commands
+    fn limits(&self) -> SearchLimits {
+        let mut limits = match (self.movetime, self.clock) {
+            (Some(movetime), _) => SearchLimits { movetime: Some(movetime), ..Default::default() },
+            (None, Some(clock)) => SearchLimits::from_clock(clock, self.inc, None),
+            (None, None) => SearchLimits { movetime: Some(Duration::from_secs(1)), ..Default::default() },
+        };
+        limits.depth = self.depth;
+        limits
+    }
+
+    fn play(&mut self, mv: &Move) {
+        let _ = self.game.make_move(mv);
+        self.report_result();
+    }
+
+    fn take_back(&mut self, n: usize) {
+        for _ in 0..n {
+            if self.game.unmake_move().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Prints the game result and enters force mode if the game is over
+    fn report_result(&mut self) {
+        self.game.update_state();
+        let result = match self.game.state() {
+            GameState::Ongoing => return,
+            GameState::Win(Color::White) => "1-0 {White mates}",
+            GameState::Win(Color::Black) => "0-1 {Black mates}",
+            GameState::Draw(DrawReason::Stalemate) => "1/2-1/2 {Stalemate}",
+            GameState::Draw(DrawReason::FiftyMoves) => "1/2-1/2 {Fifty move rule}",
+            GameState::Draw(DrawReason::SeventyFiveMoves) => "1/2-1/2 {Seventy-five move rule}",
+            GameState::Draw(DrawReason::ThreefoldRepetition | DrawReason::FivefoldRepetition) => "1/2-1/2 {Draw by repetition}",
+            GameState::Draw(DrawReason::InsufficientMaterial) => "1/2-1/2 {Insufficient material}",
+        };
+        println!("{}", result);
+        self.force = true;
+    }
+}
+
+impl Default for XBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Prints thinking output in the extended `ply score time nodes seldepth nps \t pv` format
+fn print_thinking(result: &SearchResult, game: &Board) {
+    // Mates are reported as 100000 + N for a mate in N moves, negated when the engine gets mated
+    let score = match result.mate_in() {
+        Some(moves) if moves > 0 => 100000 + moves,
+        Some(moves) => -100000 + moves,
+        None => result.score,
+    };
+    let millis = result.time.as_millis().max(1);
+    println!(
+        "{} {} {} {} {} {}\t{}",
+        result.depth,
+        score,
+        result.time.as_millis() / 10,
+        result.nodes,
+        result.seldepth,
+        result.nodes as u128 * 1000 / millis,
+        result.pv_string(game)
+    );
+    let _ = stdout().flush();
+}
+
+/// Returns whether a string looks like a move in coordinate notation (e.g. e2e4, e7e8q)
+fn is_coordinate_move(mv: &str) -> bool {
+    mv.is_ascii()
+        && (mv.len() == 4 || mv.len() == 5)
+        && square_to_index(&mv[0..2]).is_ok()
+        && square_to_index(&mv[2..4]).is_ok()
+}
+
+/// Runs a CECP session over stdin/stdout.
+/// `first` is a line that was already read from stdin before the session started
+pub fn xboard_loop(first: Option<String>) -> anyhow::Result<()> {
+    let mut xboard = XBoard::new();
+    let lines = first
+        .into_iter()
+        .chain(stdin().lock().lines().map_while(Result::ok));
+
+    for line in lines {
+        let cmd = line.split_whitespace().collect::<Vec<_>>();
+        if cmd.is_empty() {
+            continue;
+        }
+        let running = xboard.handle(&cmd);
+        stdout().flush()?;
+        if !running {
+            break;
+        }
+    }
+    Ok(())
+}
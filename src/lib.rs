@@ -0,0 +1,40 @@
+//! ChessCrab is a chess engine library: board representation, legal move generation,
+//! FEN, SAN and PGN, Zobrist hashing, evaluation and search.
+//!
+//! The types needed to work with positions are re-exported from the crate root:
+//! ```
+//! use chess_crab::Board;
+//!
+//! let mut board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+//! let mv = board.parse_san("e5").unwrap();
+//! board.make_move(&mv).unwrap();
+//! assert_eq!(board.gen_legal_moves().len(), 29);
+//! board.unmake_move();
+//! assert_eq!(board.get_hash(), board.compute_hash());
+//! ```
+//! Evaluation, search and perft live in their own modules.
+
+mod game;
+mod shared;
+pub mod evaluation;
+pub mod search;
+pub mod perft;
+#[cfg(test)]
+mod test;
+
+pub use game::{
+    moves::move_struct::{Flag, Move},
+    notation::pgn::{read_pgn, GameRecord, PgnGame},
+    structs::{
+        bitboard::Bitboard,
+        board::Board,
+        color::{Castling, Color},
+        game_state::{DrawReason, GameState},
+        piece::Piece,
+    },
+};
+pub use shared::{
+    errors::ChessError,
+    functions::{index_to_square, square_to_index},
+    statics::init_statics,
+};
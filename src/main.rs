@@ -1,34 +1,54 @@
-use std::
-    io::stdout
-;
+use std::io::{stdin, stdout, IsTerminal};
 
-use io::state::{main_loop, State};
+use io::{cli::{perft_cli, perft_suite_cli}, state::{main_loop, State}, uci::uci_loop, xboard::xboard_loop};
 use crossterm::{
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
-use game::structs::board::Board;
-use shared::statics::init_statics;
+use chess_crab::{init_statics, Board};
 
-pub mod game;
-pub mod shared;
-pub mod io;
-#[cfg(test)]
-pub mod test;
+mod io;
 
 
 fn main() -> anyhow::Result<()> {
     init_statics();
 
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        Some("perft") => return perft_cli(&args[2..]),
+        Some("perftsuite") => return perft_suite_cli(&args[2..]),
+        _ => (),
+    }
+
+    if args.iter().any(|arg| arg == "--uci") {
+        return uci_loop(None);
+    }
+    if args.iter().any(|arg| arg == "--xboard") {
+        return xboard_loop(None);
+    }
+
+    // GUIs talk to the engine through a pipe, so the protocol can be
+    // picked from the first line before anything is drawn
+    let mut first_cmd = None;
+    if !stdin().is_terminal() {
+        let mut buf = String::new();
+        stdin().read_line(&mut buf)?;
+        match buf.trim() {
+            "uci" => return uci_loop(Some(buf)),
+            "xboard" => return xboard_loop(Some(buf)),
+            _ => (),
+        }
+        first_cmd = Some(buf);
+    }
+
     let game = Board::default();
     let mut state = State::new(game);
 
     execute!(stdout(), EnterAlternateScreen)?;
 
-    main_loop(&mut state)?;
+    main_loop(&mut state, first_cmd)?;
 
     execute!(stdout(), LeaveAlternateScreen)?;
 
     Ok(())
 }
-
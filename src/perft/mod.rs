@@ -0,0 +1,139 @@
+use std::{
+    mem,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::game::{moves::move_struct::Move, structs::{board::Board, game_state::GameState}};
+
+pub use table::PerftTable;
+
+pub mod suite;
+mod table;
+
+/// Result of a perft run
+#[derive(Clone, Copy, Debug)]
+pub struct PerftResult {
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl PerftResult {
+    /// Nodes per second
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1_000_000 / self.time.as_micros().max(1)) as u64
+    }
+}
+
+impl Board {
+    /// Counts the leaf nodes of the move generation tree of a given depth.
+    /// Game termination rules are ignored, as usual for perft
+    pub fn perft(&mut self, depth: u8) -> u64 {
+        let state = mem::replace(&mut self.state, GameState::Ongoing);
+        let nodes = perft_rec(self, depth);
+        self.state = state;
+        nodes
+    }
+
+    /// Counts the leaf nodes under every legal move of the position
+    pub fn divide(&mut self, depth: u8) -> Vec<(Move, u64)> {
+        let state = mem::replace(&mut self.state, GameState::Ongoing);
+        let res = self
+            .gen_legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let _ = self.make_move(&mv);
+                let nodes = perft_rec(self, depth.saturating_sub(1));
+                self.unmake_move();
+                (mv, nodes)
+            })
+            .collect();
+        self.state = state;
+        res
+    }
+
+    /// Runs perft and measures the time it takes
+    pub fn timed_perft(&mut self, depth: u8) -> PerftResult {
+        let start = Instant::now();
+        let nodes = self.perft(depth);
+        PerftResult { nodes, time: start.elapsed() }
+    }
+
+    /// Perft that splits the root moves between `threads` workers.
+    /// Counts of positions met again through transpositions are taken from `table`
+    pub fn parallel_perft(&self, depth: u8, threads: usize, table: &PerftTable) -> PerftResult {
+        let start = Instant::now();
+        let mut root = self.clone();
+        root.state = GameState::Ongoing;
+        if depth <= 1 {
+            let nodes = perft_rec(&mut root, depth);
+            return PerftResult { nodes, time: start.elapsed() }
+        }
+
+        let moves = root.gen_legal_moves();
+        // Workers take the next unclaimed root move until none are left
+        let next = AtomicUsize::new(0);
+        let nodes = thread::scope(|scope| {
+            let workers = (0..threads.max(1))
+                .map(|_| {
+                    let mut game = root.clone();
+                    let (moves, next) = (&moves, &next);
+                    scope.spawn(move || {
+                        let mut nodes = 0;
+                        while let Some(mv) = moves.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            let _ = game.make_move(mv);
+                            nodes += hashed_perft_rec(&mut game, depth - 1, table);
+                            game.unmake_move();
+                        }
+                        nodes
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers.into_iter().map(|worker| worker.join().unwrap_or_else(|err| std::panic::resume_unwind(err))).sum()
+        });
+        PerftResult { nodes, time: start.elapsed() }
+    }
+}
+
+/// Number of threads available to the process
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+fn perft_rec(game: &mut Board, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1
+    }
+    let moves = game.gen_legal_moves();
+    // Bulk counting: leaves don't have to be played
+    if depth == 1 {
+        return moves.len() as u64
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let _ = game.make_move(&mv);
+        nodes += perft_rec(game, depth - 1);
+        game.unmake_move();
+    }
+    nodes
+}
+
+fn hashed_perft_rec(game: &mut Board, depth: u8, table: &PerftTable) -> u64 {
+    if depth <= 1 {
+        return perft_rec(game, depth)
+    }
+    if let Some(nodes) = table.probe(game.get_hash(), depth) {
+        return nodes
+    }
+
+    let mut nodes = 0;
+    for mv in game.gen_legal_moves() {
+        let _ = game.make_move(&mv);
+        nodes += hashed_perft_rec(game, depth - 1, table);
+        game.unmake_move();
+    }
+    table.store(game.get_hash(), depth, nodes);
+    nodes
+}
@@ -0,0 +1,91 @@
+use crate::{
+    game::{moves::move_struct::Move, structs::board::Board},
+    shared::errors::ChessError,
+};
+
+use super::PerftTable;
+
+/// A position of a perft suite with its expected node counts
+#[derive(Clone, Debug)]
+pub struct SuiteEntry {
+    /// Line of the suite file, starting at 1
+    pub line: usize,
+    pub fen: String,
+    /// Expected node counts by depth
+    pub depths: Vec<(u8, u64)>,
+}
+
+/// A depth of a suite position where the node count didn't match
+#[derive(Clone, Debug)]
+pub struct SuiteMismatch {
+    pub entry: SuiteEntry,
+    pub depth: u8,
+    pub expected: u64,
+    pub nodes: u64,
+    /// Node counts under every root move, to narrow the failing line down
+    pub divide: Vec<(Move, u64)>,
+}
+
+/// Parses a perft suite in the `perftsuite.epd` format, one position per line:
+/// `<fen> ;D1 20 ;D2 400 ...`. Empty lines and lines starting with `#` are skipped
+pub fn parse_suite(epd: &str) -> Result<Vec<SuiteEntry>, ChessError> {
+    let mut res = Vec::new();
+    for (i, line) in epd.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |details: &str| ChessError::EPDParseError(i + 1, details.to_string());
+
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim().to_string();
+        let mut depths = Vec::new();
+        for field in fields {
+            let (depth, nodes) = field
+                .trim()
+                .strip_prefix('D')
+                .and_then(|field| field.split_once(char::is_whitespace))
+                .ok_or(error(&format!("Invalid depth field: {}", field.trim())))?;
+            let depth = depth.parse::<u8>().map_err(|_| error(&format!("Invalid depth: {}", depth)))?;
+            let nodes = nodes.trim().parse::<u64>().map_err(|_| error(&format!("Invalid node count: {}", nodes.trim())))?;
+            depths.push((depth, nodes));
+        }
+        res.push(SuiteEntry { line: i + 1, fen, depths });
+    }
+    Ok(res)
+}
+
+/// Checks every depth of every suite position up to `max_depth`.
+/// `report` is called after each position with the mismatches found in it
+pub fn run_suite<F: FnMut(&SuiteEntry, &[SuiteMismatch])>(
+    entries: &[SuiteEntry],
+    max_depth: u8,
+    threads: usize,
+    table: &PerftTable,
+    mut report: F,
+) -> Result<Vec<SuiteMismatch>, ChessError> {
+    let mut res = Vec::new();
+    for entry in entries {
+        let mut game = Board::from_fen(&entry.fen).map_err(|err| {
+            ChessError::EPDParseError(entry.line, err.to_string().replace('\n', " "))
+        })?;
+        let mut mismatches = Vec::new();
+        for &(depth, expected) in entry.depths.iter().filter(|(depth, _)| *depth <= max_depth) {
+            let nodes = game.parallel_perft(depth, threads, table).nodes;
+            if nodes != expected {
+                mismatches.push(SuiteMismatch {
+                    entry: entry.clone(),
+                    depth,
+                    expected,
+                    nodes,
+                    divide: game.divide(depth),
+                });
+                // Deeper counts would be wrong as well
+                break;
+            }
+        }
+        report(entry, &mismatches);
+        res.extend(mismatches);
+    }
+    Ok(res)
+}
@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Hash table of perft counts shared between threads.
+/// Entries are stored lock-free: the key is saved xored with the count,
+/// so an entry torn by a concurrent write doesn't match its key and is ignored
+pub struct PerftTable {
+    entries: Vec<[AtomicU64; 2]>,
+    mask: usize,
+}
+
+impl PerftTable {
+    /// Creates a table of at most `mb` megabytes, rounded down to a power of two entries
+    pub fn new(mb: usize) -> Self {
+        let max_entries = (mb.max(1) << 20) / size_of::<[AtomicU64; 2]>();
+        let size = 1 << max_entries.ilog2();
+        Self {
+            entries: (0..size).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+            mask: size - 1,
+        }
+    }
+
+    pub fn probe(&self, hash: u64, depth: u8) -> Option<u64> {
+        let key = key(hash, depth);
+        let [check, nodes] = &self.entries[key as usize & self.mask];
+        let nodes = nodes.load(Ordering::Relaxed);
+        (check.load(Ordering::Relaxed) ^ nodes == key && nodes != 0).then_some(nodes)
+    }
+
+    pub fn store(&self, hash: u64, depth: u8, nodes: u64) {
+        let key = key(hash, depth);
+        let [check, entry] = &self.entries[key as usize & self.mask];
+        check.store(key ^ nodes, Ordering::Relaxed);
+        entry.store(nodes, Ordering::Relaxed);
+    }
+}
+
+/// Mixes the depth into the position hash, so counts of different depths don't collide
+fn key(hash: u64, depth: u8) -> u64 {
+    hash ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
@@ -0,0 +1,437 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    evaluation::evaluate,
+    game::{moves::move_struct::{Flag, Move}, structs::board::Board},
+};
+
+pub(crate) mod ordering;
+mod pruning;
+mod quiescence;
+pub(crate) mod tt;
+
+use ordering::{is_noisy, Heuristics, MovePicker};
+use pruning::*;
+use tt::{Bound, TranspositionTable};
+
+pub use tt::DEFAULT_TT_SIZE;
+
+pub const INFINITY: i32 = 32000;
+pub const MATE: i32 = 31000;
+pub const MAX_PLY: usize = 128;
+
+/// Scores above this bound are mates
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+/// Iterations from this depth on search a window around the previous score
+const ASPIRATION_DEPTH: u8 = 5;
+/// Initial half-width of the aspiration window, doubled whenever the score falls outside
+const ASPIRATION_WINDOW: i32 = 25;
+
+/// Constraints a search has to respect. Unset constraints are unlimited
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// Search until stopped, even if the depth limit is reached
+    pub infinite: bool,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u8) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Allocates time for a move from the remaining clock time, increment and the number of moves
+    /// until the next time control
+    pub fn from_clock(time: Duration, inc: Duration, movestogo: Option<u32>) -> Self {
+        let overhead = Duration::from_millis(50);
+        let moves = movestogo.unwrap_or(30).clamp(1, 50);
+        let budget = time / moves + inc * 3 / 4;
+        let movetime = budget.min(time.saturating_sub(overhead)).max(Duration::from_millis(1));
+        Self {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+}
+
+/// Result of a finished search iteration
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub pv: Vec<Move>,
+    pub depth: u8,
+    /// Deepest ply reached, including quiescence search and extensions
+    pub seldepth: usize,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Transposition table usage in permille
+    pub hashfull: usize,
+}
+
+impl SearchResult {
+    /// Returns the number of moves to mate if the score is a mate score.
+    /// Negative values mean that the side to move gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score >= MATE_BOUND {
+            Some((MATE - self.score + 1) / 2)
+        } else if self.score <= -MATE_BOUND {
+            Some(-(MATE + self.score) / 2)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the principal variation in coordinate notation.
+    /// `game` is any position of the game, which decides how castling is written
+    pub fn pv_string(&self, game: &Board) -> String {
+        self.pv
+            .iter()
+            .map(|mv| game.algebraic(mv))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Negamax alpha-beta searcher with iterative deepening
+pub struct Searcher {
+    /// Set from another thread to abort the search
+    pub stop: Arc<AtomicBool>,
+    stopped: bool,
+    /// Depth of the last finished iteration
+    completed: u8,
+    nodes: u64,
+    seldepth: usize,
+    /// Kept between searches, cleared only on request
+    tt: TranspositionTable,
+    heuristics: Heuristics,
+    limits: SearchLimits,
+    start: Instant,
+    /// Triangular principal variation table
+    pv: [[Move; MAX_PLY]; MAX_PLY],
+    pv_len: [usize; MAX_PLY],
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            stopped: false,
+            completed: 0,
+            nodes: 0,
+            seldepth: 0,
+            tt: TranspositionTable::default(),
+            heuristics: Heuristics::new(),
+            limits: SearchLimits::default(),
+            start: Instant::now(),
+            pv: [[Move::null(); MAX_PLY]; MAX_PLY],
+            pv_len: [0; MAX_PLY],
+        }
+    }
+
+    /// Searches a position with iterative deepening until one of the limits is hit.
+    /// `report` is called after every completed iteration
+    pub fn search<F: FnMut(&SearchResult)>(&mut self, game: &mut Board, limits: SearchLimits, mut report: F) -> SearchResult {
+        self.limits = limits;
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.stopped = false;
+        self.completed = 0;
+        self.tt.new_search();
+        self.heuristics.new_search();
+
+        let moves = game.gen_legal_moves();
+        let mut result = SearchResult {
+            best_move: moves.first().copied(),
+            score: 0,
+            pv: Vec::new(),
+            depth: 0,
+            seldepth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            hashfull: 0,
+        };
+        if moves.is_empty() {
+            return result;
+        }
+
+        let max_depth = limits.depth.unwrap_or(MAX_PLY as u8 - 1).min(MAX_PLY as u8 - 1);
+        for depth in 1..=max_depth {
+            let score = self.aspiration(game, depth, result.score);
+            if self.stopped {
+                break;
+            }
+
+            self.completed = depth;
+            result.pv = self.pv[0][..self.pv_len[0]].to_vec();
+            result.best_move = result.pv.first().copied().or(result.best_move);
+            result.score = score;
+            result.depth = depth;
+            result.seldepth = self.seldepth;
+            result.nodes = self.nodes;
+            result.time = self.start.elapsed();
+            result.hashfull = self.tt.hashfull();
+            report(&result);
+        }
+
+        if self.limits.infinite {
+            while !self.stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result
+    }
+
+    /// Searches the root with a narrow window around the score of the previous iteration.
+    /// A score outside the window is only a bound, so the window is widened on that side and the
+    /// search repeated until the score falls inside
+    fn aspiration(&mut self, game: &mut Board, depth: u8, previous: i32) -> i32 {
+        if depth < ASPIRATION_DEPTH {
+            return self.negamax(game, depth, 0, -INFINITY, INFINITY);
+        }
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = (previous - delta).max(-INFINITY);
+        let mut beta = (previous + delta).min(INFINITY);
+        loop {
+            let score = self.negamax(game, depth, 0, alpha, beta);
+            if self.stopped {
+                return score;
+            }
+            delta *= 2;
+            if score <= alpha {
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+        }
+    }
+
+    fn negamax(&mut self, game: &mut Board, depth: u8, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_len[ply] = ply;
+        self.seldepth = self.seldepth.max(ply);
+        if ply > 0 && (game.halfmove_clock >= 100 || game.repetitions() > 0) {
+            return 0;
+        }
+        // Check extension: the side in check has few replies, so the line is cheap to follow further
+        let in_check = game.is_check() == Some(game.turn);
+        let depth = if in_check { depth.saturating_add(1) } else { depth };
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+
+        if self.nodes & 1023 == 0 && self.should_stop() {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return 0;
+        }
+        self.nodes += 1;
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(game);
+        }
+
+        // Only nodes searched with the full window can be on the principal variation
+        let pv_node = beta - alpha > 1;
+        let hash = game.get_hash();
+        let entry = self.tt.probe(hash, ply);
+        if let Some(entry) = entry {
+            // Principal variation nodes, the root among them, always search, so that the line is complete
+            if !pv_node && entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => (),
+                }
+            }
+        }
+
+        // Nodes searched with a null window only have to prove a bound, so they may be cut
+        // on the static evaluation. Positions without pieces are left alone, as zugzwang is likely there
+        let previous = game.undo_stack.last().map(|undo| undo.mv);
+        let non_pawn_material = has_non_pawn_material(game);
+        let eval = if in_check { -INFINITY } else { evaluate(game) };
+        if !pv_node && !in_check && ply > 0 {
+            // Reverse futility pruning: even with a margin for the opponent's threats the score stays above beta
+            if non_pawn_material && depth <= RFP_DEPTH && eval - RFP_MARGIN * depth as i32 >= beta {
+                return eval;
+            }
+
+            // Razoring: far below alpha, only captures can save the position
+            if depth <= RAZOR_DEPTH && eval + RAZOR_MARGIN * (depth as i32) < alpha {
+                let score = self.quiescence(game, ply, alpha, alpha + 1);
+                if score <= alpha {
+                    return score;
+                }
+            }
+
+            // Null move pruning: if passing still fails high, a real move would too
+            let after_null = previous.is_some_and(|mv| mv.flag == Flag::Null);
+            if non_pawn_material && depth >= NULL_MOVE_DEPTH && eval >= beta && !after_null && game.make_move(&Move::null()).is_ok() {
+                let reduced = depth.saturating_sub(1 + null_move_reduction(depth));
+                let score = -self.negamax(game, reduced, ply + 1, -beta, -beta + 1);
+                game.unmake_move();
+                if self.stopped {
+                    return 0;
+                }
+                if score >= beta {
+                    // Mates found after passing aren't proven
+                    return if score >= MATE_BOUND { beta } else { score };
+                }
+            }
+        }
+
+        let moves = game.gen_legal_moves();
+        if moves.is_empty() {
+            return match in_check {
+                true => -MATE + ply as i32,
+                false => 0,
+            };
+        }
+        let tt_move = entry.and_then(|entry| entry.best_move);
+        let picker = MovePicker::new(moves, &self.heuristics, tt_move, ply, previous);
+
+        // Futility pruning: quiet moves can't raise the score to alpha
+        let futile = !pv_node
+            && !in_check
+            && non_pawn_material
+            && depth <= FUTILITY_DEPTH
+            && eval + FUTILITY_MARGIN * depth as i32 <= alpha;
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut quiets = Vec::new();
+        let mut searched = 0;
+        for mv in picker {
+            if game.make_move(&mv).is_err() {
+                continue;
+            }
+            let quiet = !is_noisy(&mv) && game.is_check().is_none();
+            if futile && quiet && searched > 0 {
+                game.unmake_move();
+                continue;
+            }
+
+            // Principal variation search: the first move is expected to be the best, the others only
+            // have to be proven worse with a null window. Late quiet moves are also searched shallower.
+            // A move that beats alpha anyway is searched again at full depth, then with the full window
+            let reduction = match quiet && !in_check && depth >= LMR_DEPTH && searched >= LMR_MOVES {
+                true => late_move_reduction(depth, searched).saturating_sub(pv_node as u8).min(depth - 2),
+                false => 0,
+            };
+            let mut score = -INFINITY;
+            if searched > 0 {
+                score = -self.negamax(game, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha);
+                if reduction > 0 && score > alpha {
+                    score = -self.negamax(game, depth - 1, ply + 1, -alpha - 1, -alpha);
+                }
+            }
+            if searched == 0 || (score > alpha && score < beta) {
+                score = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha);
+            }
+            game.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+            searched += 1;
+
+            if score > best {
+                best = score;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                self.update_pv(ply, mv);
+                if alpha >= beta {
+                    if !is_noisy(&mv) {
+                        self.heuristics.update(mv, ply, depth, previous, &quiets);
+                    }
+                    break;
+                }
+            }
+            if !is_noisy(&mv) {
+                quiets.push(mv);
+            }
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(hash, ply, depth, bound, best, best_move);
+        best
+    }
+
+    /// Reallocates the transposition table with a new size in megabytes. All entries are lost
+    pub fn resize_hash(&mut self, mb: usize) {
+        self.tt.resize(mb);
+    }
+
+    /// Empties the transposition table, e.g. before a new game
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    /// Returns the memory taken by the transposition table in bytes
+    pub fn hash_size(&self) -> usize {
+        self.tt.size()
+    }
+
+    /// Puts a move in front of the child's principal variation
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let child_len = self.pv_len[ply + 1].max(ply + 1);
+        let (parent, child) = self.pv.split_at_mut(ply + 1);
+        parent[ply][ply] = mv;
+        parent[ply][ply + 1..child_len].copy_from_slice(&child[0][ply + 1..child_len]);
+        self.pv_len[ply] = child_len;
+    }
+
+    fn should_stop(&self) -> bool {
+        // The first iteration always finishes, so that the move played has been searched
+        if self.completed == 0 {
+            return false;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        if let Some(nodes) = self.limits.nodes {
+            if self.nodes >= nodes {
+                return true;
+            }
+        }
+        if let Some(movetime) = self.limits.movetime {
+            if self.start.elapsed() >= movetime {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
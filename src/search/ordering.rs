@@ -0,0 +1,147 @@
+use std::cmp::Reverse;
+
+use crate::{
+    evaluation::MG_VALUES,
+    game::{
+        moves::move_struct::{Flag, Move},
+        structs::piece::Piece,
+    },
+};
+
+use super::MAX_PLY;
+
+const TT_MOVE_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 24;
+const KILLER_SCORES: [i32; 2] = [1 << 23, (1 << 23) - 1];
+const COUNTERMOVE_SCORE: i32 = (1 << 23) - 2;
+/// History scores are kept within this bound, below the scores of killers and countermoves
+const MAX_HISTORY: i32 = 1 << 14;
+
+/// Statistics of the moves that caused cutoffs, used to order quiet moves
+pub struct Heuristics {
+    /// Two quiet moves per ply that caused a cutoff in a sibling node
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// Butterfly table of how often a quiet move caused a cutoff, indexed by color, from and to
+    history: [[[i32; 64]; 64]; 2],
+    /// Quiet move that refuted a move, indexed by the refuted move's color, piece and destination
+    countermoves: [[[Option<Move>; 64]; 6]; 2],
+}
+
+impl Heuristics {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            history: [[[0; 64]; 64]; 2],
+            countermoves: [[[None; 64]; 6]; 2],
+        }
+    }
+
+    /// Prepares the tables for a new search. Killers are specific to a position, history is only aged
+    pub fn new_search(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        self.history.iter_mut().flatten().flatten().for_each(|score| *score /= 2);
+    }
+
+    /// Records a quiet move that caused a beta cutoff.
+    /// `tried` are the quiet moves searched before it, which didn't
+    pub fn update(&mut self, mv: Move, ply: usize, depth: u8, previous: Option<Move>, tried: &[Move]) {
+        if self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
+        if let Some(previous) = previous.filter(|previous| previous.flag != Flag::Null) {
+            self.countermoves[previous.color as usize][previous.piece as usize][previous.to as usize] = Some(mv);
+        }
+
+        let bonus = (depth as i32 * depth as i32).min(MAX_HISTORY);
+        self.add_history(mv, bonus);
+        for tried in tried {
+            self.add_history(*tried, -bonus);
+        }
+    }
+
+    /// Adds to a history score. The more extreme the score already is, the less it changes,
+    /// so that it stays within `MAX_HISTORY`
+    fn add_history(&mut self, mv: Move, bonus: i32) {
+        let score = &mut self.history[mv.color as usize][mv.from as usize][mv.to as usize];
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+    }
+
+    fn score(&self, mv: &Move, tt_move: Option<Move>, ply: usize, previous: Option<Move>) -> i32 {
+        if Some(*mv) == tt_move {
+            return TT_MOVE_SCORE
+        }
+        if is_noisy(mv) {
+            return CAPTURE_SCORE + mvv_lva(mv)
+        }
+        if let Some(killer) = self.killers[ply].iter().position(|killer| *killer == Some(*mv)) {
+            return KILLER_SCORES[killer]
+        }
+        if let Some(previous) = previous.filter(|previous| previous.flag != Flag::Null) {
+            if self.countermoves[previous.color as usize][previous.piece as usize][previous.to as usize] == Some(*mv) {
+                return COUNTERMOVE_SCORE
+            }
+        }
+        self.history[mv.color as usize][mv.from as usize][mv.to as usize]
+    }
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Yields moves from the most to the least promising: the hash move, captures and promotions
+/// by MVV-LVA, killers, the countermove and then quiet moves by history.
+/// Moves are scored up front but sorted lazily, since a cutoff often comes after the first few
+pub struct MovePicker {
+    moves: Vec<(Move, i32)>,
+    next: usize,
+}
+
+impl MovePicker {
+    /// `previous` is the move that led to the position, used to find its countermove
+    pub fn new(moves: Vec<Move>, heuristics: &Heuristics, tt_move: Option<Move>, ply: usize, previous: Option<Move>) -> Self {
+        Self {
+            moves: moves
+                .into_iter()
+                .map(|mv| (mv, heuristics.score(&mv, tt_move, ply, previous)))
+                .collect(),
+            next: 0,
+        }
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let rest = self.moves.get(self.next..)?;
+        // Ties keep the generation order
+        let (best, _) = rest.iter().enumerate().max_by_key(|(i, (_, score))| (*score, Reverse(*i)))?;
+        self.moves.swap(self.next, self.next + best);
+        self.next += 1;
+        Some(self.moves[self.next - 1].0)
+    }
+}
+
+/// Returns whether a move changes material: a capture or a promotion
+pub fn is_noisy(mv: &Move) -> bool {
+    mv.flag.is_capture() || matches!(mv.flag, Flag::Promotion(_))
+}
+
+/// Most valuable victim, least valuable attacker: captures of bigger pieces first,
+/// and among those the captures with the smallest piece. Promotions count as winning the new piece
+fn mvv_lva(mv: &Move) -> i32 {
+    let victim = match mv.flag {
+        Flag::Capture(captured) | Flag::CapturePromotion(captured, _) => MG_VALUES[captured as usize],
+        Flag::EnPassant => MG_VALUES[Piece::Pawn as usize],
+        _ => 0,
+    };
+    let promotion = match mv.flag {
+        Flag::Promotion(piece) | Flag::CapturePromotion(_, piece) => MG_VALUES[piece as usize],
+        _ => 0,
+    };
+    (victim + promotion) * 16 - MG_VALUES[mv.piece as usize] / 16
+}
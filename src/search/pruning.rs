@@ -0,0 +1,49 @@
+use lazy_static::lazy_static;
+
+use crate::game::structs::{board::Board, piece::Piece};
+
+/// Null move pruning is tried from this depth on
+pub const NULL_MOVE_DEPTH: u8 = 3;
+/// Reverse futility pruning is done up to this depth, with a margin per ply of depth
+pub const RFP_DEPTH: u8 = 6;
+pub const RFP_MARGIN: i32 = 80;
+/// Razoring is done up to this depth, with a margin per ply of depth
+pub const RAZOR_DEPTH: u8 = 2;
+pub const RAZOR_MARGIN: i32 = 250;
+/// Futility pruning is done up to this depth, with a margin per ply of depth
+pub const FUTILITY_DEPTH: u8 = 3;
+pub const FUTILITY_MARGIN: i32 = 150;
+/// Late moves are reduced from this depth on, after this many moves were searched in full
+pub const LMR_DEPTH: u8 = 3;
+pub const LMR_MOVES: usize = 3;
+
+lazy_static! {
+    /// LMR[depth][moves] is the reduction of a late move. It grows with the logarithm of both the
+    /// remaining depth and the number of moves searched before it
+    static ref LMR: [[u8; 64]; 64] = {
+        let mut res = [[0; 64]; 64];
+        for (depth, row) in res.iter_mut().enumerate().skip(1) {
+            for (moves, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction = (0.75 + (depth as f64).ln() * (moves as f64).ln() / 2.25) as u8;
+            }
+        }
+        res
+    };
+}
+
+/// Returns the reduction of a quiet move searched after `moves` others
+pub fn late_move_reduction(depth: u8, moves: usize) -> u8 {
+    LMR[(depth as usize).min(63)][moves.min(63)]
+}
+
+/// Returns how much shallower than a regular child the null move is searched
+pub fn null_move_reduction(depth: u8) -> u8 {
+    3 + depth / 6
+}
+
+/// Returns whether the side to move has pieces other than pawns and the king.
+/// Without them zugzwang is common, so passing or standing on the evaluation proves nothing
+pub fn has_non_pawn_material(game: &Board) -> bool {
+    let pieces = &game.pieces[game.turn];
+    pieces[Piece::Queen] | pieces[Piece::Rook] | pieces[Piece::Bishop] | pieces[Piece::Knight] != 0
+}
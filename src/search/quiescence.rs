@@ -0,0 +1,90 @@
+use crate::{
+    evaluation::{evaluate, MG_VALUES},
+    game::{
+        moves::move_struct::{Flag, Move},
+        structs::{board::Board, piece::Piece},
+    },
+};
+
+use super::{ordering::MovePicker, Searcher, INFINITY, MATE, MAX_PLY};
+
+/// Captures that can't raise the score to alpha even with this much to spare are skipped
+const DELTA_MARGIN: i32 = 200;
+
+impl Searcher {
+    /// Searches captures and promotions until the position is quiet, so that the evaluation
+    /// isn't taken in the middle of an exchange. The side to move may stand pat on the static
+    /// evaluation instead of capturing, unless it's in check, in which case every evasion is searched
+    pub(super) fn quiescence(&mut self, game: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_len[ply] = ply;
+        self.seldepth = self.seldepth.max(ply);
+        if self.nodes & 1023 == 0 && self.should_stop() {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return 0;
+        }
+        self.nodes += 1;
+
+        let stand_pat = evaluate(game);
+        if ply >= MAX_PLY - 1 {
+            return stand_pat;
+        }
+
+        let in_check = game.is_check() == Some(game.turn);
+        let mut best = -INFINITY;
+        let moves = if in_check {
+            let moves = game.gen_legal_moves();
+            if moves.is_empty() {
+                return -MATE + ply as i32;
+            }
+            moves
+        } else {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            best = stand_pat;
+            game.gen_legal_captures()
+        };
+
+        for mv in MovePicker::new(moves, &self.heuristics, None, ply, None) {
+            // Delta pruning: the capture is hopeless if even winning the piece doesn't reach alpha
+            if !in_check && stand_pat + material_gain(&mv) + DELTA_MARGIN <= alpha {
+                continue;
+            }
+            if game.make_move(&mv).is_err() {
+                continue;
+            }
+            let score = -self.quiescence(game, ply + 1, -beta, -alpha);
+            game.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, mv);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Returns the material a move wins: the captured piece and the promotion of a pawn
+fn material_gain(mv: &Move) -> i32 {
+    let promotion = |piece: Piece| MG_VALUES[piece as usize] - MG_VALUES[Piece::Pawn as usize];
+    match mv.flag {
+        Flag::Capture(captured) => MG_VALUES[captured as usize],
+        Flag::EnPassant => MG_VALUES[Piece::Pawn as usize],
+        Flag::Promotion(piece) => promotion(piece),
+        Flag::CapturePromotion(captured, piece) => MG_VALUES[captured as usize] + promotion(piece),
+        _ => 0,
+    }
+}
@@ -0,0 +1,138 @@
+use crate::game::moves::move_struct::Move;
+
+use super::MATE_BOUND;
+
+/// Size of the transposition table when none is set, in megabytes
+pub const DEFAULT_TT_SIZE: usize = 16;
+
+/// How a stored score relates to the true score of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The search failed high, the true score is at least this
+    Lower,
+    /// The search failed low, the true score is at most this
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TTEntry {
+    /// Upper half of the hash. The lower half is implied by the slot
+    key: u32,
+    /// Search generation the entry was stored in
+    age: u8,
+    pub depth: u8,
+    pub bound: Bound,
+    pub score: i32,
+    pub best_move: Option<Move>,
+}
+
+/// Transposition table of search results, indexed by the Zobrist hash of the position.
+/// One entry per slot, replaced unless the stored one is from the current search and deeper
+pub struct TranspositionTable {
+    entries: Vec<Option<TTEntry>>,
+    mask: usize,
+    age: u8,
+}
+
+impl TranspositionTable {
+    /// Creates a table of at most `mb` megabytes, rounded down to a power of two entries
+    pub fn new(mb: usize) -> Self {
+        let max_entries = (mb.max(1) << 20) / size_of::<Option<TTEntry>>();
+        let size = 1 << max_entries.ilog2();
+        Self {
+            entries: vec![None; size],
+            mask: size - 1,
+            age: 0,
+        }
+    }
+
+    /// Reallocates the table with a new size. All entries are lost
+    pub fn resize(&mut self, mb: usize) {
+        *self = Self::new(mb);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.age = 0;
+    }
+
+    /// Marks the entries stored so far as old, so that a new search replaces them first
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    /// Returns the memory taken by the entries in bytes
+    pub fn size(&self) -> usize {
+        self.entries.len() * size_of::<Option<TTEntry>>()
+    }
+
+    /// Returns how full the table is in permille, estimated from the first thousand slots.
+    /// Entries left from earlier searches count as free
+    pub fn hashfull(&self) -> usize {
+        let sample = &self.entries[..self.entries.len().min(1000)];
+        let used = sample.iter().flatten().filter(|entry| entry.age == self.age).count();
+        used * 1000 / sample.len()
+    }
+
+    /// Returns the entry of a position. Mate scores are converted to be relative to `ply`
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<TTEntry> {
+        let entry = self.entries[hash as usize & self.mask]?;
+        if entry.key != (hash >> 32) as u32 {
+            return None
+        }
+        Some(TTEntry { score: score_from_tt(entry.score, ply), ..entry })
+    }
+
+    pub fn store(&mut self, hash: u64, ply: usize, depth: u8, bound: Bound, score: i32, best_move: Option<Move>) {
+        let key = (hash >> 32) as u32;
+        let slot = &mut self.entries[hash as usize & self.mask];
+        if let Some(old) = slot {
+            if old.age == self.age && old.depth > depth && bound != Bound::Exact {
+                return
+            }
+        }
+        // A position searched again keeps its move if the new search didn't find one
+        let best_move = match slot {
+            Some(old) if old.key == key => best_move.or(old.best_move),
+            _ => best_move,
+        };
+        *slot = Some(TTEntry {
+            key,
+            age: self.age,
+            depth,
+            bound,
+            score: score_to_tt(score, ply),
+            best_move,
+        });
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TT_SIZE)
+    }
+}
+
+/// Mate scores count the distance from the root. In the table they are stored as the distance
+/// from the position itself, so they stay correct when the position is reached at another ply
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
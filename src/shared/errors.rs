@@ -5,6 +5,8 @@ pub enum ChessError{
     SquareParseError(String),
     InvalidMove(String),
     FENParseError(String, String),
+    PGNParseError(String),
+    EPDParseError(usize, String),
     GameFinished,
     InvalidPosition
 }
@@ -15,6 +17,8 @@ impl Display for ChessError{
             ChessError::InvalidMove(m) => write!(f, "{}", m),
             ChessError::SquareParseError(sq) => write!(f, "Couldn't parse square: {:?}", sq),
             ChessError::FENParseError(fen, details) => write!(f, "Couldn't parse FEN string.\nInput: {}\nDetails: {}", fen, details),
+            ChessError::PGNParseError(details) => write!(f, "Couldn't parse PGN.\nDetails: {}", details),
+            ChessError::EPDParseError(line, details) => write!(f, "Couldn't parse EPD line {}: {}", line, details),
             ChessError::GameFinished => write!(f, "Couldn't make a move, game is finished."),
             ChessError::InvalidPosition => write!(f, "Invalid position")
         }
@@ -1,13 +1,17 @@
-use crate::shared::statics::consts::{FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H, RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8};
-use crate::{game::structs::bitboard::Bitboard, shared::errors::ChessError};
+use crate::shared::errors::ChessError;
 
 pub fn square_to_index(square: &str) -> Result<u8, ChessError>{
     let files = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
     let ranks = ['1', '2', '3', '4', '5', '6', '7', '8'];
 
+    let mut chars = square.chars();
+    let (Some(file), Some(rank), None) = (chars.next(), chars.next(), chars.next()) else {
+        return Err(ChessError::SquareParseError(square.to_string()))
+    };
+
     let file = files
         .iter()
-        .position(|&x| x == square.chars().next().unwrap());
+        .position(|&x| x == file);
     let file = match file {
         None => return Err(ChessError::SquareParseError(square.to_string())),
         Some(f) => f as u8
@@ -15,7 +19,7 @@ pub fn square_to_index(square: &str) -> Result<u8, ChessError>{
 
     let rank = ranks
         .iter()
-        .position(|&x| x == square.chars().nth(1).unwrap());
+        .position(|&x| x == rank);
     let rank = match rank {
         None => return Err(ChessError::SquareParseError(square.to_string())),
         Some(r) => r as u8
@@ -34,31 +38,3 @@ pub fn index_to_square(index: u8) -> String {
     let letter = letters[(index % 8) as usize];
     format!("{}{}", letter, rank)
 }
-
-pub fn col(square: u8) -> Bitboard {
-    match (square) % 8 {
-        7 => *FILE_A,
-        6 => *FILE_B,
-        5 => *FILE_C,
-        4 => *FILE_D,
-        3 => *FILE_E,
-        2 => *FILE_F,
-        1 => *FILE_G,
-        0 => *FILE_H,
-        _ => unreachable!()
-    }
-}
-
-pub fn row(square: u8) -> Bitboard {
-    match (square) / 8 {
-        7 => *RANK_8,
-        6 => *RANK_7,
-        5 => *RANK_6,
-        4 => *RANK_5,
-        3 => *RANK_4,
-        2 => *RANK_3,
-        1 => *RANK_2,
-        0 => *RANK_1,
-        _ => unreachable!()
-    }
-}
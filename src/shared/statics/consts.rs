@@ -25,6 +25,7 @@ lazy_static!{
     pub static ref FILE_C: Bitboard = Bitboard::from(0b0000010000000100000001000000010000000100000001000000010000000100u64);
     pub static ref FILE_B: Bitboard = Bitboard::from(0b0000001000000010000000100000001000000010000000100000001000000010u64);
     pub static ref FILE_A: Bitboard = Bitboard::from(0b0000000100000001000000010000000100000001000000010000000100000001u64);
+    pub static ref DARK_SQUARES: Bitboard = Bitboard::from(0xAA55AA55AA55AA55u64);
     pub static ref FILES: [Bitboard; 8] = [
         *FILE_A, *FILE_B, *FILE_C, *FILE_D, *FILE_E, *FILE_F, *FILE_G, *FILE_H
     ];
@@ -52,3 +53,6 @@ lazy_static!{
         ]
     ];
 }
+
+/// Starting squares of the castling rooks in standard chess, indexed by color and castling side
+pub const CASTLING_ROOKS: [[u8; 2]; 2] = [[7, 0], [63, 56]];
@@ -0,0 +1,148 @@
+use lazy_static::lazy_static;
+
+use crate::{game::structs::bitboard::Bitboard, shared::structs::DIRECTION};
+
+use super::{consts::{FILE_A, FILE_H, RANK_1, RANK_8}, rays::RAY};
+
+/// Magic bitboard entry of a single square
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Magic {
+    /// Relevant occupancy, i.e. the slider's rays without the board edges
+    pub mask: Bitboard,
+    pub magic: u64,
+    pub shift: u32,
+    /// Index of the square's first entry in the attack table
+    pub offset: usize,
+}
+
+impl Magic {
+    pub fn index(&self, occupancy: Bitboard) -> usize {
+        let relevant = (occupancy & self.mask).num();
+        self.offset + (relevant.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+lazy_static! {
+    pub static ref ROOK_MAGICS: ([Magic; 64], Vec<Bitboard>) = gen_magics(ROOK_DIRECTIONS);
+    pub static ref BISHOP_MAGICS: ([Magic; 64], Vec<Bitboard>) = gen_magics(BISHOP_DIRECTIONS);
+}
+
+const ROOK_DIRECTIONS: [DIRECTION; 4] = [DIRECTION::N, DIRECTION::E, DIRECTION::S, DIRECTION::W];
+const BISHOP_DIRECTIONS: [DIRECTION; 4] = [DIRECTION::NE, DIRECTION::SE, DIRECTION::SW, DIRECTION::NW];
+
+/// Returns a bitboard of squares attacked by a rook, including blockers of both colors
+pub fn rook_attacks(square: u8, occupancy: Bitboard) -> Bitboard {
+    let (magics, attacks) = &*ROOK_MAGICS;
+    attacks[magics[square as usize].index(occupancy)]
+}
+
+/// Returns a bitboard of squares attacked by a bishop, including blockers of both colors
+pub fn bishop_attacks(square: u8, occupancy: Bitboard) -> Bitboard {
+    let (magics, attacks) = &*BISHOP_MAGICS;
+    attacks[magics[square as usize].index(occupancy)]
+}
+
+/// Finds magic numbers for every square by trial and error and fills the attack table
+fn gen_magics(directions: [DIRECTION; 4]) -> ([Magic; 64], Vec<Bitboard>) {
+    let mut magics = [Magic::default(); 64];
+    let mut table = Vec::new();
+    for square in 0..64u8 {
+        let mut rng = XorShift(SEEDS[square as usize / 8]);
+        let mask = relevant_mask(square, directions);
+        let bits = mask.count();
+
+        // Enumerate all subsets of the mask with the Carry-Rippler trick
+        let mut occupancies = Vec::with_capacity(1 << bits);
+        let mut subset = 0u64;
+        loop {
+            let occupancy = Bitboard::from(subset);
+            occupancies.push((occupancy, slow_attacks(square, occupancy, directions).num()));
+            subset = subset.wrapping_sub(mask.num()) & mask.num();
+            if subset == 0 {
+                break;
+            }
+        }
+
+        // `epochs` marks which entries were filled by the current candidate,
+        // so the table doesn't have to be cleared after every failed attempt
+        let mut entries = vec![0u64; 1 << bits];
+        let mut epochs = vec![0u32; 1 << bits];
+        let mut epoch = 0;
+        let magic = loop {
+            let candidate = rng.sparse();
+            if (mask.num().wrapping_mul(candidate) >> 56).count_ones() < 6 {
+                continue;
+            }
+            epoch += 1;
+            let shift = 64 - bits;
+
+            let fits = occupancies.iter().all(|(occupancy, attacks)| {
+                let index = (occupancy.num().wrapping_mul(candidate) >> shift) as usize;
+                if epochs[index] != epoch {
+                    epochs[index] = epoch;
+                    entries[index] = *attacks;
+                    true
+                } else {
+                    entries[index] == *attacks
+                }
+            });
+            if fits {
+                break Magic { mask, magic: candidate, shift, offset: 0 };
+            }
+        };
+
+        magics[square as usize] = Magic { offset: table.len(), ..magic };
+        table.extend(entries.into_iter().map(Bitboard::from));
+    }
+
+    (magics, table)
+}
+
+/// Returns the slider's rays from a square without the last square of each ray
+fn relevant_mask(square: u8, directions: [DIRECTION; 4]) -> Bitboard {
+    directions.iter().fold(Bitboard::empty(), |acc, direction| {
+        let edge = match direction {
+            DIRECTION::N => *RANK_8,
+            DIRECTION::S => *RANK_1,
+            DIRECTION::E => *FILE_H,
+            DIRECTION::W => *FILE_A,
+            _ => *RANK_1 | *RANK_8 | *FILE_A | *FILE_H,
+        };
+        acc | (RAY[square as usize][*direction as usize] & !edge)
+    })
+}
+
+/// Computes slider attacks ray by ray. Used only to fill the tables
+fn slow_attacks(square: u8, occupancy: Bitboard, directions: [DIRECTION; 4]) -> Bitboard {
+    directions.iter().fold(Bitboard::empty(), |acc, direction| {
+        let ray = RAY[square as usize][*direction as usize];
+        let blocker = match direction {
+            DIRECTION::W | DIRECTION::S | DIRECTION::SE | DIRECTION::SW => (ray & occupancy).msb_index(),
+            _ => (ray & occupancy).lsb_index(),
+        };
+        acc | match blocker {
+            Some(blocker) => ray & !RAY[blocker as usize][*direction as usize],
+            None => ray,
+        }
+    })
+}
+
+/// Per-rank generator seeds that are known to find magics quickly
+const SEEDS: [u64; 8] = [728, 10316, 55013, 32803, 12281, 15100, 16645, 255];
+
+/// Deterministic xorshift* generator, so the same magics are found on every run
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(2685821657736338717)
+    }
+
+    /// Random number with few bits set, which makes a good magic candidate
+    fn sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
@@ -1,9 +1,11 @@
 use consts::{FILES, PIECES, RANKS};
+use magics::{BISHOP_MAGICS, ROOK_MAGICS};
 use masks::{KING_MASKS, KNIGHT_MASKS, PAWN_CAPTURE_MASKS};
-use rays::{INCL_RAY, RAY};
-use zobrist::{BLACK_MOVE_KEY, CASTLING_KEYS, PIECE_KEYS};
+use rays::{BETWEEN, INCL_RAY, RAY};
+use zobrist::{BLACK_MOVE_KEY, CASTLING_KEYS, EN_PASSANT_KEYS, PIECE_KEYS};
 
 pub mod masks;
+pub mod magics;
 pub mod rays;
 pub mod zobrist;
 pub mod consts;
@@ -11,11 +13,15 @@ pub mod consts;
 pub fn init_statics() {
     let _ = RAY[0][0];
     let _ = INCL_RAY[0][0];
+    let _ = BETWEEN[0][0];
     let _ = PAWN_CAPTURE_MASKS[0][0];
     let _ = KING_MASKS[0];
     let _ = KNIGHT_MASKS[0];
+    let _ = ROOK_MAGICS.0[0];
+    let _ = BISHOP_MAGICS.0[0];
     let _ = PIECE_KEYS[0];
     let _ = CASTLING_KEYS[0];
+    let _ = EN_PASSANT_KEYS[0];
     let _ = BLACK_MOVE_KEY;
     let _ = PIECES[0];
     let _ = RANKS[0];
@@ -26,6 +26,21 @@ lazy_static! {
         });
         res
     };
+
+    /// BETWEEN[from][to] are the squares strictly between two squares sharing a line.
+    /// Empty if the squares don't share a line
+    pub static ref BETWEEN: [[Bitboard; 64]; 64] = {
+        let mut res = [[Bitboard::empty(); 64]; 64];
+        (0..64).for_each(|from| {
+            (0..8).for_each(|direction| {
+                let ray = RAY[from][direction];
+                for to in ray {
+                    res[from][to as usize] = ray & !RAY[to as usize][direction] & !Bitboard::from(to);
+                }
+            });
+        });
+        res
+    };
 }
 
 
@@ -7,7 +7,33 @@ pub static ZOBRIST_SUMMAND: u64 = 0x9940fc28ae2b;
 lazy_static! {
     pub static ref PIECE_KEYS: [[[u64; 6]; 2]; 64] = Zobrist::constants();
     pub static ref BLACK_MOVE_KEY: u64 = Zobrist::next(PIECE_KEYS[63][1][5]);
-    pub static ref CASTLING_KEYS: [[u64; 2]; 2] = Zobrist::castlings(*BLACK_MOVE_KEY);
+    /// Keys of castling rights, indexed by color and the file of the rook the right refers to
+    pub static ref CASTLING_KEYS: [[u64; 8]; 2] = Zobrist::castlings(*BLACK_MOVE_KEY);
+    /// Keys of the en passant file, indexed by file
+    pub static ref EN_PASSANT_KEYS: [u64; 8] = Zobrist::en_passants(CASTLING_KEYS[1][7]);
+}
+
+/// Returns the key of an en passant square, or 0 if there is none
+pub fn en_passant_key(en_passant: Option<u8>) -> u64 {
+    match en_passant {
+        Some(square) => EN_PASSANT_KEYS[(square % 8) as usize],
+        None => 0,
+    }
+}
+
+/// Returns the combined key of all castling rights that are set.
+/// Rights are told apart by their rook, so Chess960 positions that differ only in the rook
+/// a right refers to get different keys
+pub fn castling_key(castling_rights: &[[bool; 2]; 2], castling_rooks: &[[u8; 2]; 2]) -> u64 {
+    let mut key = 0;
+    for (color, rights) in castling_rights.iter().enumerate() {
+        for (side, right) in rights.iter().enumerate() {
+            if *right {
+                key ^= CASTLING_KEYS[color][(castling_rooks[color][side] % 8) as usize];
+            }
+        }
+    }
+    key
 }
 
 struct Zobrist {}
@@ -32,14 +58,25 @@ impl Zobrist {
         res
     }
 
-    fn castlings(prev: u64) -> [[u64; 2]; 2]{
-        let white_kingside = Zobrist::next(prev);
-        let white_queenside = Zobrist::next(white_kingside);
-        let black_kingside = Zobrist::next(white_queenside);
-        let black_queenside = Zobrist::next(black_kingside);
-        [
-            [white_kingside, white_queenside],
-            [black_kingside, black_queenside]
-        ]
+    fn castlings(prev: u64) -> [[u64; 8]; 2]{
+        let mut res = [[0; 8]; 2];
+        let mut prev = prev;
+        (0..2).for_each(|side| {
+            (0..8).for_each(|file| {
+                prev = Zobrist::next(prev);
+                res[side][file] = prev;
+            });
+        });
+        res
+    }
+
+    fn en_passants(prev: u64) -> [u64; 8] {
+        let mut res = [0; 8];
+        let mut prev = prev;
+        (0..8).for_each(|file| {
+            prev = Zobrist::next(prev);
+            res[file] = prev;
+        });
+        res
     }
 }
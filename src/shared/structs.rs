@@ -1,4 +1,5 @@
 #[derive(Copy, Clone, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum DIRECTION {
     N,
     NE,
@@ -1,4 +1,10 @@
-use crate::game::{moves::{individual::{all_pawn_captures, bishop_moves, king_moves, knight_moves, pawn_moves, rook_moves}, move_struct::{Flag, Move}}, structs::{bitboard::Bitboard, board::Board, color::Color, piece::Piece}};
+use crate::evaluation::{evaluate, game_phase, MAX_PHASE};
+use crate::game::structs::game_state::{DrawReason, GameState};
+use crate::shared::{errors::ChessError, statics::consts::DARK_SQUARES};
+use crate::game::notation::pgn::{read_pgn, GameRecord};
+use crate::search::{ordering::{is_noisy, Heuristics, MovePicker}, tt::{Bound, TTEntry, TranspositionTable}, SearchLimits, Searcher, MATE, MATE_BOUND};
+use crate::perft::{default_threads, suite::{parse_suite, run_suite}, PerftTable};
+use crate::game::{moves::{individual::{all_pawn_captures, bishop_moves, king_moves, knight_moves, pawn_moves, rook_moves}, move_struct::{Flag, Move}}, structs::{bitboard::Bitboard, board::Board, color::{Castling, Color}, piece::Piece}};
 
 
 #[test]
@@ -720,7 +726,7 @@ fn move_flag_detection() {
 }
 
 // [nodes, captures, en_passant, castles, promotions, checks, checkmates(?)]
-fn perft_rec(game: &Board, depth: u32, current_depth: u32) -> [usize; 6] {
+fn perft_rec(game: &mut Board, depth: u32, current_depth: u32) -> [usize; 6] {
     let mut perft_res = [0; 6];
     let legal_moves = game.gen_legal_moves();
     if legal_moves.is_empty() {
@@ -729,49 +735,28 @@ fn perft_rec(game: &Board, depth: u32, current_depth: u32) -> [usize; 6] {
     if current_depth == depth {
         for mv in legal_moves{
             perft_res[0] += 1;
-            let mut new_game = game.clone();
-            let _ = new_game.make_move(&mv);
             match mv.flag {
-                // Flag::Default | Flag::LongPawnMove => perft_res[0] += 1,
-                Flag::Capture(_) => {
-                    perft_res[1] += 1;
-                    // println!("depth: {}, current_depth: {}, move: {:?}", depth, current_depth, mv);
-                }
-                Flag::EnPassant => { 
-                    perft_res[1] += 1; perft_res[2] += 1;
-                    // println!("depth: {}, current_depth: {}, move: {:?}", depth, current_depth, mv);
-                    // println!("prev: {}", game);
-                    // println!("next: {}", new_game);
-                },
-                Flag::Promotion(_) => {
-                    perft_res[4] += 1;
-                    // println!("depth: {}, current_depth: {}, move: {:?}", depth, current_depth, mv);
-                    // println!("{}", new_game);
-                },
-                Flag::CapturePromotion(_, _) => { 
-                    perft_res[4] += 1; perft_res[1] += 1;
-                    // println!("depth: {}, current_depth: {}, move: {:?}", depth, current_depth, mv);
-                    // println!("{}", new_game);
-                },
-                Flag::LongCastling | Flag::ShortCastling => {
-                    perft_res[3] += 1;
-                    // println!("depth: {}, current_depth: {}, move: {:?}", depth, current_depth, mv);
-                    // println!("{}", new_game);
-                }
+                Flag::Capture(_) => perft_res[1] += 1,
+                Flag::EnPassant => { perft_res[1] += 1; perft_res[2] += 1; },
+                Flag::Promotion(_) => perft_res[4] += 1,
+                Flag::CapturePromotion(_, _) => { perft_res[4] += 1; perft_res[1] += 1; },
+                Flag::LongCastling | Flag::ShortCastling => perft_res[3] += 1,
                 _ => ()
             }
-            if new_game.is_check().is_some() { 
+            let _ = game.make_move(&mv);
+            if game.is_check().is_some() { 
                 perft_res[5] += 1;
             };
+            game.unmake_move();
         };
 
     }else{
         for mv in legal_moves{
-            let mut new_game = game.clone();
-            let _ = new_game.make_move(&mv);
-            for (perft_res, new) in perft_res.iter_mut().zip(perft_rec(&new_game, depth, current_depth + 1)){
+            let _ = game.make_move(&mv);
+            for (perft_res, new) in perft_res.iter_mut().zip(perft_rec(game, depth, current_depth + 1)){
                 *perft_res += new;
             }
+            game.unmake_move();
         }
     }
     perft_res
@@ -781,90 +766,821 @@ fn perft_rec(game: &Board, depth: u32, current_depth: u32) -> [usize; 6] {
 /// https://www.chessprogramming.org/Perft_Results
 #[test]
 fn perft_init(){
-    let game = Board::default();
+    let mut game = Board::default();
 
-    let perft = perft_rec(&game, 1, 1);
+    let perft = perft_rec(&mut game, 1, 1);
     assert_eq!(perft, [20, 0,  0, 0, 0, 0]);
 
-    let perft = perft_rec(&game, 2, 1);
+    let perft = perft_rec(&mut game, 2, 1);
     assert_eq!(perft, [400, 0, 0, 0, 0, 0]);
 
-    let perft = perft_rec(&game, 3, 1);
+    let perft = perft_rec(&mut game, 3, 1);
     assert_eq!(perft, [8902, 34, 0, 0, 0, 12]);
 
-    let perft = perft_rec(&game, 4, 1);
+    let perft = perft_rec(&mut game, 4, 1);
     assert_eq!(perft, [197281, 1576, 0, 0, 0, 469]);
 
-    // not fast enough for this
-    // let nodes = perft_rec(&game, 5, 1);
-    // assert_eq!(nodes, 4865609);
+    let table = PerftTable::new(16);
+    assert_eq!(game.parallel_perft(5, 4, &table).nodes, 4865609);
+}
+
+/// Deep perft of the chessprogramming.org positions.
+/// Run with `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn perft_deep(){
+    let table = PerftTable::new(256);
+    for (fen, depth, nodes) in [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 7, 3195901860),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 6, 8031647685),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 7, 178633661),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 6, 706045033),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 5, 89941194),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 6, 6923051137),
+    ] {
+        let game = Board::from_fen(fen).unwrap();
+        assert_eq!(game.parallel_perft(depth, default_threads(), &table).nodes, nodes, "{}", fen);
+    }
 }
 
 #[test]
 fn kiwipete(){
-    let game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let mut game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
 
-    let perft = perft_rec(&game, 1, 1);
+    let perft = perft_rec(&mut game, 1, 1);
     assert_eq!(perft, [48, 8, 0, 2, 0, 0]);
-    let perft = perft_rec(&game, 2, 1);
+    let perft = perft_rec(&mut game, 2, 1);
     assert_eq!(perft, [2039, 351, 1, 91, 0, 3]);
-    let perft = perft_rec(&game, 3, 1);
+    let perft = perft_rec(&mut game, 3, 1);
     assert_eq!(perft, [97862, 17102, 45, 3162, 0, 993]);
-    let perft = perft_rec(&game, 4, 1);
+    let perft = perft_rec(&mut game, 4, 1);
     assert_eq!(perft, [4085603, 757163, 1929, 128013, 15172, 25523]);
 }
 
 #[test]
 fn perft_pos_3(){
-    let game = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+    let mut game = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
 
-    let perft = perft_rec(&game, 1, 1);
+    let perft = perft_rec(&mut game, 1, 1);
     assert_eq!(perft, [14, 1, 0, 0, 0, 2]);
-    let perft = perft_rec(&game, 2, 1);
+    let perft = perft_rec(&mut game, 2, 1);
     assert_eq!(perft, [191, 14, 0, 0, 0, 10]);
-    let perft = perft_rec(&game, 3, 1);
+    let perft = perft_rec(&mut game, 3, 1);
     assert_eq!(perft, [2812, 209, 2, 0, 0, 267]);
-    let perft = perft_rec(&game, 4, 1);
+    let perft = perft_rec(&mut game, 4, 1);
     assert_eq!(perft, [43238, 3348, 123, 0, 0, 1680]);
-    let perft = perft_rec(&game, 5, 1);
+    let perft = perft_rec(&mut game, 5, 1);
     assert_eq!(perft, [674624, 52051, 1165, 0, 0, 52950]);
 }
 
 #[test]
 fn perft_pos_4(){
-    let game = Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+    let mut game = Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
 
-    let perft = perft_rec(&game, 1, 1);
+    let perft = perft_rec(&mut game, 1, 1);
     assert_eq!(perft, [6, 0, 0, 0, 0, 0]);
-    let perft = perft_rec(&game, 2, 1);
+    let perft = perft_rec(&mut game, 2, 1);
     assert_eq!(perft, [264, 87, 0, 6, 48, 10]);
-    let perft = perft_rec(&game, 3, 1);
+    let perft = perft_rec(&mut game, 3, 1);
     assert_eq!(perft, [9467, 1021, 4, 0, 120, 38]);
-    let perft = perft_rec(&game, 4, 1);
+    let perft = perft_rec(&mut game, 4, 1);
     assert_eq!(perft[0], 422333);
 }
 
 #[test]
 fn perft_pos_5(){
-    let game = Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+    let mut game = Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
 
-    let perft = perft_rec(&game, 1, 1);
+    let perft = perft_rec(&mut game, 1, 1);
     assert_eq!(perft[0], 44);
-    let perft = perft_rec(&game, 2, 1);
+    let perft = perft_rec(&mut game, 2, 1);
     assert_eq!(perft[0], 1486);
-    let perft = perft_rec(&game, 3, 1);
+    let perft = perft_rec(&mut game, 3, 1);
     assert_eq!(perft[0], 62379);
 }
 
 #[test]
 fn perft_pos_6(){
-    let game = Board::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap();
-    let perft = perft_rec(&game, 1, 1)[0];
+    let mut game = Board::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap();
+    let perft = perft_rec(&mut game, 1, 1)[0];
     assert_eq!(perft, 46);
-    let perft = perft_rec(&game, 2, 1)[0];
+    let perft = perft_rec(&mut game, 2, 1)[0];
     assert_eq!(perft, 2079);
-    let perft = perft_rec(&game, 3, 1)[0];
+    let perft = perft_rec(&mut game, 3, 1)[0];
     assert_eq!(perft, 89890);
-    let perft = perft_rec(&game, 4, 1)[0];
+    let perft = perft_rec(&mut game, 4, 1)[0];
     assert_eq!(perft,  3894594);
 }
+
+#[test]
+fn evaluation_symmetry(){
+    let game = Board::default();
+    assert_eq!(evaluate(&game), 0);
+    assert_eq!(game_phase(&game), MAX_PHASE);
+
+    // Same position with colors swapped and the board mirrored
+    let game = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+    let mirrored = Board::from_fen("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4").unwrap();
+    assert_eq!(evaluate(&game), evaluate(&mirrored));
+
+    // Extra queen is good for its owner regardless of side to move
+    let white = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    let black = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+    assert!(evaluate(&white) > 0);
+    assert_eq!(evaluate(&white), -evaluate(&black));
+    assert_eq!(game_phase(&white), 4);
+}
+
+#[test]
+fn make_unmake(){
+    let mut game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let fen = game.to_fen();
+    let hash = game.get_hash();
+
+    for mv in game.gen_legal_moves() {
+        let _ = game.make_move(&mv);
+        for reply in game.gen_legal_moves() {
+            let _ = game.make_move(&reply);
+            assert_eq!(game.unmake_move(), Some(reply));
+        }
+        assert_eq!(game.unmake_move(), Some(mv));
+        assert_eq!(game.to_fen(), fen, "{}", mv.algebraic());
+        assert_eq!(game.get_hash(), hash);
+    }
+    assert_eq!(game.unmake_move(), None);
+}
+
+#[test]
+fn null_move(){
+    // Passing keeps the pieces, castling rights and clocks
+    let fen = "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2";
+    let mut game = Board::from_fen(fen).unwrap();
+    game.make_move(&Move::null()).unwrap();
+    assert_eq!(game.to_fen(), "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 2 2");
+    assert_eq!(game.get_hash(), game.compute_hash());
+    game.unmake_move();
+    assert_eq!(game.to_fen(), fen);
+    assert_eq!(game.get_hash(), game.compute_hash());
+
+    // but the en passant capture is lost
+    let mut game = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+    play(&mut game, "e2e4");
+    game.make_move(&Move::null()).unwrap();
+    assert_eq!(game.to_fen(), "4k3/8/8/8/3pP3/8/8/4K3 w - - 0 1");
+    assert_eq!(game.get_hash(), game.compute_hash());
+    game.unmake_move();
+    assert_eq!(game.to_fen(), "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+
+    // Positions before a null move don't repeat after it, although the clock doesn't count the null move
+    let mut game = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    play(&mut game, "e1e2 e8d8 e2e1 d8e8");
+    assert_eq!(game.repetitions(), 1);
+    game.make_move(&Move::null()).unwrap();
+    play(&mut game, "e8d8 e1e2 d8d7 e2e1 d7e8");
+    assert_eq!(game.halfmove_clock, 9);
+    assert_eq!(game.repetitions(), 0);
+    play(&mut game, "e1e2 e8d8 e2e1 d8e8");
+    assert_eq!(game.repetitions(), 1);
+}
+
+fn hash_walk(game: &mut Board, depth: u32) {
+    assert_eq!(game.get_hash(), game.compute_hash(), "{}", game.to_fen());
+    if depth == 0 {
+        return
+    }
+    for mv in game.gen_legal_moves() {
+        let hash = game.get_hash();
+        let _ = game.make_move(&mv);
+        hash_walk(game, depth - 1);
+        game.unmake_move();
+        assert_eq!(game.get_hash(), hash);
+    }
+}
+
+#[test]
+fn incremental_hash(){
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ] {
+        let mut game = Board::from_fen(fen).unwrap();
+        hash_walk(&mut game, 3);
+    }
+}
+
+fn play(game: &mut Board, moves: &str) {
+    for mv in moves.split_whitespace() {
+        let mv = game.parse_move(mv).unwrap();
+        game.make_move(&mv).unwrap();
+    }
+}
+
+#[test]
+fn repetition(){
+    let mut game = Board::default();
+    play(&mut game, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(game.repetitions(), 1);
+    play(&mut game, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(game.repetitions(), 2);
+    assert_eq!(game.history().count(), 8);
+    assert_eq!(game.repetition_history().len(), 9);
+    assert_eq!(game.repetition_history()[0], game.get_hash());
+    // positions before a pawn move can't repeat
+    play(&mut game, "e2e4 e7e5 g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(game.repetitions(), 1);
+
+    // capturable en passant is a part of the position
+    let mut game = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+    play(&mut game, "e2e4");
+    assert_eq!(game.en_passant, Some(20));
+    assert_eq!(game.get_hash(), Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap().get_hash());
+    assert_ne!(game.get_hash(), Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap().get_hash());
+
+    // but not when the capture would expose the king
+    let mut game = Board::from_fen("8/8/8/8/k2p3R/8/4P3/4K3 w - - 0 1").unwrap();
+    play(&mut game, "e2e4");
+    assert_eq!(game.en_passant, None);
+    assert_eq!(game.get_hash(), Board::from_fen("8/8/8/8/k2pP2R/8/8/4K3 b - e3 0 1").unwrap().get_hash());
+    assert!(Board::from_fen("8/8/8/8/k2pP2R/8/8/4K3 b - e4 0 1").is_err());
+}
+
+#[test]
+fn legal_move_edge_cases(){
+    // en passant would expose the king along the rank
+    let game = Board::from_fen("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").unwrap();
+    assert!(game.gen_legal_moves().iter().all(|mv| mv.flag != Flag::EnPassant));
+
+    // en passant captures the checking pawn
+    let game = Board::from_fen("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").unwrap();
+    assert!(game.gen_legal_moves().iter().any(|mv| mv.flag == Flag::EnPassant));
+
+    // double check leaves only king moves
+    let game = Board::from_fen("4k3/8/8/8/8/3n4/8/R3K2r w Q - 0 1").unwrap();
+    let moves = game.gen_legal_moves();
+    assert!(!moves.is_empty());
+    assert!(moves.iter().all(|mv| mv.piece == Piece::King));
+
+    // the king can't step back along the checking line
+    let game = Board::from_fen("4k3/4r3/8/8/8/8/4K3/8 w - - 0 1").unwrap();
+    assert!(game.gen_legal_moves().iter().all(|mv| mv.to != 4));
+}
+
+#[test]
+fn game_termination(){
+    let mut game = Board::default();
+    play(&mut game, "f2f3 e7e5 g2g4 d8h4");
+    game.update_state();
+    assert_eq!(game.state, GameState::Win(Color::Black));
+    let mv = game.parse_move("a2a3").unwrap();
+    assert!(matches!(game.make_move(&mv), Err(ChessError::GameFinished)));
+    game.unmake_move();
+    assert_eq!(game.state, GameState::Ongoing);
+
+    let mut game = Board::default();
+    play(&mut game, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+    game.update_state();
+    assert_eq!(game.state, GameState::Draw(DrawReason::ThreefoldRepetition));
+
+    for (fen, state) in [
+        ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", GameState::Draw(DrawReason::Stalemate)),
+        ("7k/8/6K1/8/8/8/8/8 w - - 0 1", GameState::Draw(DrawReason::InsufficientMaterial)),
+        ("7k/8/6K1/8/8/8/8/5N2 w - - 0 1", GameState::Draw(DrawReason::InsufficientMaterial)),
+        ("7k/8/6K1/3b4/8/8/8/5B2 w - - 0 1", GameState::Draw(DrawReason::InsufficientMaterial)),
+        ("7k/8/6K1/2b5/8/8/8/5B2 w - - 0 1", GameState::Ongoing),
+        ("7k/8/6K1/8/8/8/8/4NN2 w - - 0 1", GameState::Ongoing),
+        ("7k/8/6K1/8/8/8/8/5R2 w - - 99 80", GameState::Ongoing),
+        ("7k/8/6K1/8/8/8/8/5R2 w - - 100 80", GameState::Draw(DrawReason::FiftyMoves)),
+        ("7k/8/6K1/8/8/8/8/5R2 w - - 150 80", GameState::Draw(DrawReason::SeventyFiveMoves)),
+    ] {
+        let mut game = Board::from_fen(fen).unwrap();
+        game.update_state();
+        assert_eq!(game.state, state, "{}", fen);
+    }
+}
+
+#[test]
+fn fen_round_trip(){
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 37 112",
+    ] {
+        assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+    }
+    // missing clocks default to a fresh count
+    let game = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -").unwrap();
+    assert_eq!(game.to_fen(), "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+    assert!(Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w").is_err());
+    assert!(Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - x 1").is_err());
+
+    let mut game = Board::default();
+    play(&mut game, "e2e4 e7e5 g1f3");
+    assert_eq!(game.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+    game.unmake_move();
+    game.unmake_move();
+    assert_eq!(game.fullmove_number, 1);
+
+    // The position is read through accessors
+    let game = Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBN1 w Qkq f6 0 3").unwrap();
+    assert_eq!(game.turn(), Color::White);
+    assert_eq!(game.pieces(Color::White, Piece::Pawn).count(), 8);
+    assert_eq!((game.occupancy().count(), game.empty_squares().count()), (31, 33));
+    assert!(game.castling_right(Color::White, Castling::QueenSide));
+    assert!(!game.castling_right(Color::White, Castling::KingSide));
+    assert_eq!(game.en_passant(), Some(45));
+    assert_eq!((game.halfmove_clock(), game.fullmove_number()), (0, 3));
+    assert!(!game.is_chess960());
+}
+
+#[test]
+fn fifty_move_rule(){
+    let mut game = Board::from_fen("7k/8/6K1/8/8/8/8/5R2 w - - 98 80").unwrap();
+    play(&mut game, "f1f2");
+    game.update_state();
+    assert_eq!(game.state, GameState::Ongoing);
+    assert_eq!(game.halfmove_clock, 99);
+    play(&mut game, "h8g8");
+    game.update_state();
+    assert_eq!(game.state, GameState::Draw(DrawReason::FiftyMoves));
+    assert_eq!(game.to_fen(), "6k1/8/6K1/8/8/8/5R2/8 w - - 100 81");
+    game.unmake_move();
+    assert_eq!(game.state, GameState::Ongoing);
+    assert_eq!(game.halfmove_clock, 99);
+
+    // a capture or a pawn move on the hundredth halfmove resets the clock
+    let mut game = Board::from_fen("7k/8/6K1/8/8/8/p7/1R6 b - - 99 80").unwrap();
+    play(&mut game, "h8g8");
+    assert_eq!(game.halfmove_clock, 100);
+    game.unmake_move();
+    play(&mut game, "a2a1q");
+    assert_eq!(game.halfmove_clock, 0);
+
+    // checkmate on the hundredth halfmove takes precedence
+    let mut game = Board::from_fen("7k/8/6K1/8/8/8/8/5R2 w - - 99 80").unwrap();
+    play(&mut game, "f1f8");
+    game.update_state();
+    assert_eq!(game.state, GameState::Win(Color::White));
+}
+
+fn san(fen: &str, mv: &str) -> String {
+    let mut game = Board::from_fen(fen).unwrap();
+    let mv = game.parse_move(mv).unwrap();
+    game.to_san(&mv)
+}
+
+#[test]
+fn san_output(){
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert_eq!(san(start, "g1f3"), "Nf3");
+    assert_eq!(san(start, "e2e4"), "e4");
+
+    // disambiguation by file, rank and full square
+    assert_eq!(san("4k3/8/8/8/8/4K3/8/R6R w - - 0 1", "a1d1"), "Rad1");
+    assert_eq!(san("4k3/8/8/8/R7/4K3/8/R7 w - - 0 1", "a1a3"), "R1a3");
+    assert_eq!(san("4k3/8/8/8/8/Q3K3/8/Q1Q5 w - - 0 1", "a1b2"), "Qa1b2");
+    // a pinned piece doesn't make the move ambiguous
+    assert_eq!(san("k7/8/8/1N6/4r3/8/4N3/4K3 w - - 0 1", "b5d4"), "Nd4");
+
+    assert_eq!(san("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"), "exd5");
+    assert_eq!(san("4k3/8/8/4Pp2/8/8/8/4K3 w - f6 0 1", "e5f6"), "exf6");
+    assert_eq!(san("7k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"), "e8=Q+");
+    assert_eq!(san("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "e8c8"), "O-O-O");
+    assert_eq!(san("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "d8h4"), "Qh4#");
+
+    // check markers are written in a finished game too, and the game stays finished
+    let mut game = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2").unwrap();
+    game.state = GameState::Draw(DrawReason::ThreefoldRepetition);
+    let mv = game.parse_move("d8h4").unwrap();
+    assert_eq!(game.to_san(&mv), "Qh4#");
+    assert_eq!(game.state(), GameState::Draw(DrawReason::ThreefoldRepetition));
+}
+
+#[test]
+fn san_parsing(){
+    let game = Board::default();
+    assert_eq!(game.parse_san("Nf3").unwrap().algebraic(), "g1f3");
+    assert_eq!(game.parse_san("e4!?").unwrap().algebraic(), "e2e4");
+    assert_eq!(game.parse_san("e2e4").unwrap().algebraic(), "e2e4");
+    assert_eq!(game.parse_san("Pe4").unwrap().algebraic(), "e2e4");
+    assert!(game.parse_san("Nd2").is_err());
+    assert!(game.parse_san("Ke2").is_err());
+    assert!(game.parse_san("Zf3").is_err());
+    assert!(game.parse_san("").is_err());
+
+    let game = Board::from_fen("r3k2r/p1pn1ppp/8/3p4/4P3/8/PPP2PPP/R3K1NR b KQkq - 0 1").unwrap();
+    assert_eq!(game.parse_san("dxe4").unwrap().algebraic(), "d5e4");
+    assert_eq!(game.parse_san("de4").unwrap().algebraic(), "d5e4");
+    assert_eq!(game.parse_san("Pxe4").unwrap().algebraic(), "d5e4");
+    assert_eq!(game.parse_san("O-O-O").unwrap().algebraic(), "e8c8");
+    assert_eq!(game.parse_san("0-0").unwrap().algebraic(), "e8g8");
+    assert_eq!(game.parse_san("Nb6").unwrap().algebraic(), "d7b6");
+
+    // disambiguation
+    let game = Board::from_fen("4k3/8/8/8/R7/4K3/8/R6R w - - 0 1").unwrap();
+    assert!(matches!(game.parse_san("Ra2"), Err(ChessError::InvalidMove(msg)) if msg.starts_with("Ambiguous")));
+    assert_eq!(game.parse_san("R1a2").unwrap().algebraic(), "a1a2");
+    assert_eq!(game.parse_san("Rhd1").unwrap().algebraic(), "h1d1");
+    assert_eq!(game.parse_san("Ra1d1").unwrap().algebraic(), "a1d1");
+
+    // promotions
+    let game = Board::from_fen("1n5k/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(game.parse_san("a8=Q+").unwrap().algebraic(), "a7a8q");
+    assert_eq!(game.parse_san("axb8N").unwrap().algebraic(), "a7b8n");
+    assert_eq!(game.parse_san("a8=b").unwrap().algebraic(), "a7a8b");
+    assert!(game.parse_san("a8").is_err());
+
+    // SAN output reads back as the same move
+    let mut game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let fen = game.to_fen();
+    let legal = game.gen_legal_moves();
+    for mv in legal.iter() {
+        let san = game.to_san_with(mv, &legal);
+        assert_eq!(san, game.to_san(mv));
+        assert_eq!(game.parse_san(&san).unwrap(), *mv);
+        game.make_move(mv).unwrap();
+        for reply in game.gen_legal_moves() {
+            let san = game.to_san(&reply);
+            assert_eq!(game.parse_san(&san).unwrap(), reply);
+        }
+        game.unmake_move();
+    }
+    // Checks are found by making the move, which is taken back
+    assert_eq!(game.to_fen(), fen);
+    assert_eq!(game.get_hash(), game.compute_hash());
+
+    assert!(game.parse_move("z9a1").is_err());
+    assert!(game.parse_move("e7e8x").is_err());
+    assert!(game.parse_move("é2e4").is_err());
+}
+
+#[test]
+fn pgn_import(){
+    let pgn = r#"[Event "Casual game"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+% escaped line
+1. e4 e5 2. f4 exf4 {King's Gambit accepted} 3. Bc4 $6 Qh4+ 4. Kf1 b5?!
+(4... Nf6 5. Nc3 (5. Nf3 Qh6) 5... c6) ; rest of the line is a comment 5... c6
+5.Bxb5 Nf6 6.Nf3 Qh6 7.d3 Nh5 8.Nh4 Qg5 9.Nf5 c6 10.g4 Nf6 11.Rg1 cxb5 12.h4 Qg6
+13.h5 Qg5 14.Qf3 Ng8 15.Bxf4 Qf6 16.Nc3 Bc5 17.Nd5 Qxb2 18.Bd6 Bxg1 19.e5 Qxa1+
+20.Ke2 Na6 21.Nxg7+ Kd8 22.Qf6+ Nxf6 23.Be7# 1-0
+"#;
+    let game = read_pgn(pgn).unwrap();
+    assert_eq!(game.tag("White"), Some("Anderssen, Adolf"));
+    assert_eq!(game.result, "1-0");
+    assert_eq!(game.moves.len(), 45);
+    assert_eq!(game.board.to_fen(), "r1bk3r/p2pBpNp/n4n2/1p1NP2P/6P1/3P4/P1P1K3/q5b1 b - - 1 23");
+
+    let game = read_pgn("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n\n1. O-O-O Kf7 *").unwrap();
+    assert_eq!(game.start.to_fen(), "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+    assert_eq!(game.board.to_fen(), "8/5k2/8/8/8/8/8/2KR4 w - - 2 2");
+    assert_eq!(game.result, "*");
+
+    let err = read_pgn("1. e4 e5 2. Nf3 Nf6 3. Ke3").err().unwrap();
+    assert!(err.to_string().contains("Ply 5 (3.Ke3)"), "{}", err);
+    let err = read_pgn("1. e4 e5 2. Nf3 Ke6").err().unwrap();
+    assert!(err.to_string().contains("Ply 4 (2...Ke6)"), "{}", err);
+    assert!(read_pgn("1. e4 (1. d4 e5").is_err());
+
+    // Separate annotations are skipped, and the tags of a next game end the first one
+    let game = read_pgn("[Event \"First\"]\n\n1. e4 ! e5 ?! 2. Nf3\n\n[Event \"Second\"]\n\n1. d4 d5 *").unwrap();
+    assert_eq!(game.tag("Event"), Some("First"));
+    assert_eq!(game.moves.len(), 3);
+    assert_eq!(game.result, "*");
+}
+
+#[test]
+fn pgn_export(){
+    let mut game = Board::default();
+    play(&mut game, "f2f3 e7e5 g2g4 d8h4");
+    let mut record = GameRecord::from_board(&game);
+    record.set_tag("White", "Fool");
+    record.set_tag("Annotator", "Quote \"and\" backslash \\");
+    assert_eq!(record.to_pgn().unwrap(), r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "Fool"]
+[Black "?"]
+[Result "0-1"]
+[Annotator "Quote \"and\" backslash \\"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#);
+
+    // games starting from a position with Black to move
+    let mut game = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 7").unwrap();
+    play(&mut game, "e8d7 e1c1");
+    let pgn = GameRecord::from_board(&game).to_pgn().unwrap();
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 7\"]\n"));
+    assert!(pgn.ends_with("\n7... Kd7 8. O-O-O+ *\n"));
+
+    // long games are wrapped and read back unchanged
+    let pgn = "1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 5. Bxb5 Nf6 6. Nf3 Qh6 7. d3 Nh5 8. Nh4 Qg5
+                9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6 13. h5 Qg5 14. Qf3 Ng8 15. Bxf4 Qf6 1-0";
+    let loaded = read_pgn(pgn).unwrap();
+    let exported = loaded.record().to_pgn().unwrap();
+    assert!(exported.lines().all(|line| line.len() <= 80));
+    assert!(exported.contains("[Result \"1-0\"]"));
+    let reloaded = read_pgn(&exported).unwrap();
+    assert_eq!(reloaded.moves, loaded.moves);
+    assert_eq!(reloaded.result, "1-0");
+}
+
+#[test]
+fn perft_divide(){
+    let mut game = Board::default();
+    assert_eq!(game.perft(0), 1);
+    assert_eq!(game.perft(3), 8902);
+
+    let mut game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let divide = game.divide(3);
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97862);
+    let (_, castling) = divide.iter().find(|(mv, _)| mv.flag == Flag::ShortCastling).unwrap();
+    assert_eq!(*castling, 2059);
+    assert_eq!(game.to_fen(), "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+}
+
+#[test]
+fn perft_suite(){
+    let epd = "# comment
+
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902 ;D6 119060324
+4k3/8/8/8/8/8/8/4K2R w K - 0 1 ;D1 15 ;D2 67 ;D3 1197
+";
+    let entries = parse_suite(epd).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].line, 3);
+    assert_eq!(entries[0].depths[3], (6, 119060324));
+
+    let mut reported = 0;
+    let mismatches = run_suite(&entries, 3, 2, &PerftTable::new(1), |_, _| reported += 1).unwrap();
+    assert_eq!(reported, 2);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!((mismatches[0].depth, mismatches[0].expected, mismatches[0].nodes), (2, 67, 66));
+    assert_eq!(mismatches[0].divide.len(), 15);
+
+    assert!(matches!(parse_suite("8/8/8/8/8/8/8/K6k w - - ;D1 x"), Err(ChessError::EPDParseError(1, _))));
+}
+
+#[test]
+fn chess960_positions(){
+    assert_eq!(Board::chess960(518).unwrap().to_fen(), Board::default().to_fen());
+    assert_eq!(Board::chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert!(Board::chess960(960).is_none());
+
+    let mut fens = (0..960).map(|n| Board::chess960(n).unwrap().to_fen()).collect::<Vec<_>>();
+    fens.sort();
+    fens.dedup();
+    assert_eq!(fens.len(), 960);
+    for n in 0..960 {
+        let game = Board::chess960(n).unwrap();
+        let bishops = game.pieces[Color::White][Piece::Bishop];
+        assert_eq!(bishops.count(), 2);
+        assert_eq!((bishops & *DARK_SQUARES).count(), 1, "{}", n);
+        let king = game.pieces[Color::White][Piece::King].lsb_index().unwrap();
+        let (king_side, queen_side) = (game.castling_rook(Color::White, Castling::KingSide), game.castling_rook(Color::White, Castling::QueenSide));
+        assert!(queen_side < king && king < king_side, "{}", n);
+    }
+}
+
+#[test]
+fn chess960_castling(){
+    // Shredder-FEN and X-FEN castling fields describe the same position
+    let shredder = Board::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    let xfen = Board::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9").unwrap();
+    assert!(shredder.chess960 && xfen.chess960);
+    assert_eq!(shredder.castling_rooks, xfen.castling_rooks);
+    assert_eq!(shredder.to_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+    assert!(!Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap().chess960);
+
+    // An inner rook is written by its file
+    let fen = "1r2kr2/8/8/8/8/8/8/1R1RK1R1 w DGf - 0 1";
+    let game = Board::from_fen(fen).unwrap();
+    assert_eq!(game.castling_rooks[0], [6, 3]);
+    assert_eq!(game.to_fen(), "1r2kr2/8/8/8/8/8/8/1R1RK1R1 w KDk - 0 1");
+    assert_eq!(Board::from_fen(&game.to_fen()).unwrap().castling_rooks, game.castling_rooks);
+
+    // The rook a right refers to is a part of the hash, so a shared perft table tells the positions apart
+    let mut inner = Board::from_fen("4k3/8/8/8/8/8/8/4KR1R w F - 0 1").unwrap();
+    let mut outer = Board::from_fen("4k3/8/8/8/8/8/8/4KR1R w H - 0 1").unwrap();
+    assert_ne!(inner.get_hash(), outer.get_hash());
+    let table = PerftTable::new(1);
+    for game in [&mut inner, &mut outer] {
+        assert_eq!(game.parallel_perft(3, 1, &table).nodes, game.perft(3));
+    }
+
+    for (fen, depths) in [
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189, 326672]),
+        ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002, 667366]),
+        ("rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w HAha - 0 9", [27, 916, 25798, 890435]),
+    ] {
+        let mut game = Board::from_fen(fen).unwrap();
+        for (depth, nodes) in depths.into_iter().enumerate() {
+            assert_eq!(game.perft(depth as u8 + 1), nodes, "{} depth {}", fen, depth + 1);
+        }
+    }
+
+    // The king and the rook swap places, written as the king taking its own rook
+    let mut game = Board::from_fen("4k3/8/8/8/8/8/8/5KR1 w G - 0 1").unwrap();
+    let castling = game.parse_move("f1g1").unwrap();
+    assert_eq!(castling.flag, Flag::ShortCastling);
+    assert!(game.gen_legal_moves().contains(&castling));
+    assert_eq!(game.algebraic(&castling), "f1g1");
+    assert_eq!(game.to_san(&castling), "O-O");
+    game.make_move(&castling).unwrap();
+    assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+    assert_eq!(game.hash, game.compute_hash());
+    game.unmake_move();
+    assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5KR1 w K - 0 1");
+
+    // The king stays in place and only the rook moves
+    let mut game = Board::from_fen("4k3/8/8/8/8/8/8/6KR w H - 0 1").unwrap();
+    game.make_move(&game.parse_move("g1h1").unwrap()).unwrap();
+    assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+
+    // The castling rook shields the king from a rook on the back rank
+    let game = Board::from_fen("4k3/8/8/8/8/8/8/rRK5 w B - 0 1").unwrap();
+    assert!(!game.gen_legal_moves().iter().any(|mv| mv.flag.is_castling()));
+}
+
+#[test]
+fn legal_captures(){
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ] {
+        let game = Board::from_fen(fen).unwrap();
+        let expected = game.gen_legal_moves()
+            .into_iter()
+            .filter(|mv| mv.flag.is_capture() || matches!(mv.flag, Flag::Promotion(_)))
+            .collect::<Vec<_>>();
+        let captures = game.gen_legal_captures();
+        assert_eq!(captures.len(), expected.len(), "{}", fen);
+        assert!(captures.iter().all(|mv| expected.contains(mv)), "{}", fen);
+    }
+}
+
+#[test]
+fn stopped_search(){
+    // A search stopped before it starts still finishes the first iteration and plays a searched move
+    let mut game = Board::default();
+    let mut searcher = Searcher::new();
+    searcher.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    let result = searcher.search(&mut game, SearchLimits::default(), |_| ());
+    assert!(result.depth >= 1);
+    assert_eq!(result.best_move, result.pv.first().copied());
+    assert!(game.gen_legal_moves().contains(&result.best_move.unwrap()));
+
+    let limits = SearchLimits { nodes: Some(1), ..Default::default() };
+    let result = Searcher::new().search(&mut game, limits, |_| ());
+    assert!(result.depth >= 1);
+}
+
+#[test]
+fn quiescence_search(){
+    // Without looking past the horizon, the queen would take a pawn defended by a pawn
+    let mut game = Board::from_fen("4k3/8/4p3/3p4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+    let result = Searcher::new().search(&mut game, SearchLimits::depth(1), |_| ());
+    assert_ne!(result.best_move.map(|mv| mv.algebraic()), Some("d2d5".to_string()));
+    assert!(result.score > 0 && result.score < 1000);
+
+    // Check evasions are searched, so a mate on the horizon is seen at depth 1
+    let mut game = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let result = Searcher::new().search(&mut game, SearchLimits::depth(1), |_| ());
+    assert_eq!(result.best_move.map(|mv| mv.algebraic()), Some("a1a8".to_string()));
+    assert_eq!(result.mate_in(), Some(1));
+    assert_eq!(game.to_fen(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+}
+
+#[test]
+fn selective_search(){
+    // Mates behind a sacrifice and a quiet-looking first move survive the pruning
+    let fen = "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1";
+    let mut game = Board::from_fen(fen).unwrap();
+    let result = Searcher::new().search(&mut game, SearchLimits::depth(5), |_| ());
+    assert_eq!(result.pv_string(&game), "d5f6 g7f6 c4f7");
+    assert_eq!(result.mate_in(), Some(2));
+    // Null moves are taken back like any other move
+    assert_eq!(game.to_fen(), fen);
+
+    let mut game = Board::from_fen("6k1/pp4p1/2p5/2bp4/8/P5Pb/1P3rrP/2BRRN1K b - - 0 1").unwrap();
+    let result = Searcher::new().search(&mut game, SearchLimits::depth(4), |_| ());
+    assert_eq!(result.best_move.map(|mv| mv.algebraic()), Some("g2g1".to_string()));
+    assert_eq!(result.mate_in(), Some(2));
+
+    // Reductions and pruning make deeper searches affordable
+    let mut game = Board::default();
+    let result = Searcher::new().search(&mut game, SearchLimits::depth(7), |_| ());
+    assert_eq!(result.depth, 7);
+    assert!(result.nodes < 1_000_000);
+}
+
+#[test]
+fn principal_variation_search(){
+    let mut game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let mut reports = Vec::new();
+    // A small table, so that the sampled usage isn't zero
+    let mut searcher = Searcher::new();
+    searcher.resize_hash(1);
+    let result = searcher.search(&mut game, SearchLimits::depth(6), |result| reports.push(result.clone()));
+    assert_eq!(reports.iter().map(|report| report.depth).collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6]);
+    // Aspiration windows are widened until the score is exact, so every iteration has a principal variation
+    assert!(reports.iter().all(|report| !report.pv.is_empty() && report.score.abs() < MATE_BOUND));
+    assert!(reports.iter().all(|report| report.seldepth > report.depth as usize));
+    assert!(reports.windows(2).all(|pair| pair[0].nodes < pair[1].nodes));
+    assert!(result.hashfull > 0 && result.hashfull <= 1000);
+    assert_eq!(result.best_move, result.pv.first().copied());
+}
+
+#[test]
+fn transposition_table(){
+    let mut tt = TranspositionTable::new(1);
+    let game = Board::default();
+    let hash = game.get_hash();
+    let mv = game.parse_move("e2e4").unwrap();
+    assert!(tt.probe(hash, 0).is_none());
+    assert_eq!(tt.hashfull(), 0);
+
+    tt.store(hash, 0, 5, Bound::Exact, 35, Some(mv));
+    let entry = tt.probe(hash, 0).unwrap();
+    assert_eq!((entry.depth, entry.bound, entry.score, entry.best_move), (5, Bound::Exact, 35, Some(mv)));
+    // Another position in the same slot is told apart by the key bits
+    assert!(tt.probe(hash ^ (1 << 40), 0).is_none());
+
+    // A shallower result doesn't replace a deeper one of the same search, but does after a new search
+    tt.store(hash, 0, 3, Bound::Lower, 10, None);
+    assert_eq!(tt.probe(hash, 0).unwrap().depth, 5);
+    tt.new_search();
+    tt.store(hash, 0, 3, Bound::Lower, 10, None);
+    let entry = tt.probe(hash, 0).unwrap();
+    assert_eq!((entry.depth, entry.bound, entry.best_move), (3, Bound::Lower, Some(mv)));
+
+    // Mate scores are stored relative to the position, so they are right at another ply
+    tt.store(hash, 3, 2, Bound::Exact, MATE - 5, None);
+    assert_eq!(tt.probe(hash, 1).unwrap().score, MATE - 3);
+    tt.store(hash, 3, 2, Bound::Exact, -MATE + 5, None);
+    assert_eq!(tt.probe(hash, 1).unwrap().score, -MATE + 3);
+
+    // Only the first thousand slots are sampled
+    for slot in 0..500 {
+        tt.store(slot, 0, 1, Bound::Exact, 0, None);
+    }
+    assert_eq!(tt.hashfull(), 500);
+    tt.new_search();
+    assert_eq!(tt.hashfull(), 0);
+
+    tt.clear();
+    assert!(tt.probe(hash, 0).is_none());
+    // The size is rounded down to a power of two entries that fit in the given memory
+    tt.resize(64);
+    let entries = tt.size() / size_of::<Option<TTEntry>>();
+    assert!(entries.is_power_of_two());
+    assert!(tt.size() <= 64 << 20 && tt.size() * 2 > 64 << 20);
+
+    // A repeated search finds the same move, with the earlier results to start from
+    let mut game = Board::default();
+    let mut searcher = Searcher::new();
+    let first = searcher.search(&mut game, SearchLimits::depth(4), |_| ());
+    let second = searcher.search(&mut game, SearchLimits::depth(4), |_| ());
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(first.score, second.score);
+    assert!(second.nodes < first.nodes);
+}
+
+#[test]
+fn move_ordering(){
+    let game = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let moves = game.gen_legal_moves();
+    let tt_move = game.parse_move("a2a3").unwrap();
+    let killer = game.parse_move("a1b1").unwrap();
+    let counter = game.parse_move("e1d1").unwrap();
+    let quiet = game.parse_move("g2g3").unwrap();
+    let previous = Move::new(&game, 59, 60, Piece::King, Color::Black);
+    let mut heuristics = Heuristics::new();
+    heuristics.update(quiet, 5, 4, None, &[]);
+    heuristics.update(counter, 5, 4, Some(previous), &[]);
+    heuristics.update(killer, 0, 4, None, &[]);
+
+    let picked = MovePicker::new(moves.clone(), &heuristics, Some(tt_move), 0, Some(previous)).collect::<Vec<_>>();
+    assert_eq!(picked.len(), moves.len());
+    assert!(moves.iter().all(|mv| picked.contains(mv)));
+
+    // The hash move, captures from the most valuable victim down, the killer and the countermove,
+    // then the quiet move with the best history
+    let captures = moves.iter().filter(|mv| is_noisy(mv)).count();
+    assert_eq!(picked[0], tt_move);
+    assert!(picked[1..=captures].iter().all(is_noisy));
+    assert_eq!(picked[1], game.parse_move("e2a6").unwrap());
+    assert!(matches!(picked[captures].flag, Flag::Capture(Piece::Pawn)));
+    assert_eq!(picked[captures + 1..captures + 4], [killer, counter, quiet]);
+}